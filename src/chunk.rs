@@ -0,0 +1,91 @@
+//! Chunk headers shared by every ILFF chunk.
+
+use std::io::{self, Read};
+
+use byteorder::{LittleEndian, ReadBytesExt};
+
+/// `NAME` chunk, holds the name of the resource that follows it.
+pub const CHUNK_TYPE_NAME: u32 = 0x454D414E; // 'NAME'
+/// `BODY` chunk, holds the resource data itself.
+pub const CHUNK_TYPE_BODY: u32 = 0x59444F42; // 'BODY'
+
+/// The four fields that precede the payload of every chunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkHeader {
+    pub chunk_type: u32,
+    /// Size of the payload in bytes, without padding.
+    pub buffer_size: u32,
+    /// The payload is padded so that the next chunk starts on this boundary.
+    pub alignment: u32,
+    pub chunk_size: u32,
+}
+
+impl ChunkHeader {
+    /// Size of the header on disk.
+    pub const SIZE: u32 = 16;
+
+    /// Reads the next chunk header, or `None` when the reader is already at
+    /// the end of the file.
+    pub fn read<R: Read>(reader: &mut R) -> io::Result<Option<Self>> {
+        let chunk_type = match reader.read_u32::<LittleEndian>() {
+            Ok(chunk_type) => chunk_type,
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        Ok(Some(Self {
+            chunk_type,
+            buffer_size: reader.read_u32::<LittleEndian>()?,
+            alignment: reader.read_u32::<LittleEndian>()?,
+            chunk_size: reader.read_u32::<LittleEndian>()?,
+        }))
+    }
+}
+
+/// A chunk as it appears in the file, see [`crate::IlffFile`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chunk {
+    pub tag: u32,
+    pub alignment: u32,
+    pub buffer_size: u32,
+    /// Value of the last header field, kept as read.
+    pub chunk_size: u32,
+    /// Position of the chunk header in the file.
+    pub offset: u64,
+    /// The `buffer_size` bytes after the header.
+    pub payload: Vec<u8>,
+    /// The bytes between the payload and the next chunk, kept as read. These
+    /// are usually [`Chunk::padding`] zeros, but can be anything, and the
+    /// last chunk of a file may stop short of its alignment.
+    pub trailing: Vec<u8>,
+}
+
+impl Chunk {
+    /// Position of the first payload byte in the file.
+    pub fn payload_offset(&self) -> u64 {
+        self.offset + ChunkHeader::SIZE as u64
+    }
+
+    /// Position right after the payload, before any padding.
+    pub fn end(&self) -> u64 {
+        self.payload_offset() + self.buffer_size as u64
+    }
+
+    /// Number of padding bytes that bring the next chunk onto `alignment`.
+    pub fn padding(&self) -> u64 {
+        padding(self.end(), self.alignment)
+    }
+}
+
+/// Number of bytes needed after `pos` to reach a multiple of `alignment`.
+pub(crate) fn padding(pos: u64, alignment: u32) -> u64 {
+    let alignment = alignment.max(1) as u64;
+    (alignment - pos % alignment) % alignment
+}
+
+/// Renders a chunk or resource type as its four ASCII characters, e.g. `NAME`.
+pub fn fourcc(tag: u32) -> String {
+    tag.to_le_bytes()
+        .iter()
+        .map(|&b| if b.is_ascii_graphic() { b as char } else { '.' })
+        .collect()
+}
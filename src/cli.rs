@@ -0,0 +1,246 @@
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use clap::{Parser, Subcommand};
+use resviewer::chunk::fourcc;
+use resviewer::export::{self, display_name};
+use resviewer::pack::{self, Manifest};
+use resviewer::resource;
+use resviewer::{ImageResource, PixelFormat, ResourceFile, ResourceKind, TextureHeader, RES_TYPE_IRES};
+
+/// Viewer and tools for the ILFF (.res) texture archives and loose .tex
+/// files of IGI 1 and IGI 2.
+///
+/// Without a subcommand the graphical viewer is started, opening any files
+/// given.
+#[derive(Parser)]
+#[command(version)]
+pub struct Cli {
+    /// Print the parser's debug log to stderr.
+    #[arg(short, long, global = true)]
+    pub verbose: bool,
+
+    /// Files to open in the viewer; each after the first gets its own window.
+    pub files: Vec<PathBuf>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// List the textures in a file.
+    List { file: PathBuf },
+    /// Show the file header and a summary of its chunks.
+    Info { file: PathBuf },
+    /// Write every texture to a directory as PNG.
+    Extract {
+        file: PathBuf,
+        /// Directory to write to, created if missing.
+        #[arg(short, long, default_value = ".")]
+        output: PathBuf,
+        /// Also write each frame of atlases as a PNG of its own.
+        #[arg(long)]
+        frames: bool,
+    },
+    /// Check that a file parses and that every texture can be decoded.
+    Validate { file: PathBuf },
+    /// Pack the PNGs in a directory into a new .res archive.
+    Pack {
+        dir: PathBuf,
+        /// Archive to write.
+        #[arg(short, long)]
+        output: PathBuf,
+        /// Pixel format for images the manifest doesn't give one for
+        /// [default: argb1555].
+        #[arg(short, long)]
+        format: Option<PixelFormat>,
+        /// TOML or JSON file listing names, order and per-image formats.
+        #[arg(short, long)]
+        manifest: Option<PathBuf>,
+        /// Generate mip levels for every image.
+        #[arg(long)]
+        mipmaps: bool,
+    },
+    /// Start the graphical viewer, optionally opening files.
+    Gui { files: Vec<PathBuf> },
+}
+
+/// Runs a headless subcommand; exits with 1 when the file can't be read.
+pub fn run(command: Command, verbose: bool) -> ExitCode {
+    let mut debug_log = Vec::new();
+    let result = match command {
+        Command::List { file } => list(&file, &mut debug_log),
+        Command::Info { file } => info(&file, &mut debug_log),
+        Command::Extract { file, output, frames } => extract(&file, &output, frames, &mut debug_log),
+        Command::Validate { file } => validate(&file, &mut debug_log),
+        Command::Pack { dir, output, format, manifest, mipmaps } => {
+            pack(&dir, &output, format, manifest.as_deref(), mipmaps)
+        }
+        Command::Gui { .. } => unreachable!("the GUI is started by main"),
+    };
+    if verbose {
+        for line in &debug_log {
+            eprintln!("{}", line);
+        }
+    }
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn load(file: &Path, debug_log: &mut Vec<String>) -> anyhow::Result<(ResourceFile, Vec<ImageResource>)> {
+    let resource = ResourceFile::open(file, debug_log)?;
+    let images = resource.images(debug_log)?;
+    Ok((resource, images))
+}
+
+fn list(file: &Path, debug_log: &mut Vec<String>) -> anyhow::Result<()> {
+    let (resource, images) = load(file, debug_log)?;
+    for (i, image) in images.iter().enumerate() {
+        println!(
+            "{:4}  {:<32}  {:>9}  {:<8}  {} levels  {} bytes",
+            i,
+            display_name(image, i),
+            format!("{}x{}", image.width, image.height),
+            image.format,
+            image.level_count(),
+            image.raw.len()
+        );
+    }
+    if let Some(ilff) = resource.as_ilff() {
+        for other in resource::other_resources(ilff, &images) {
+            println!(
+                "   -  {:<32}  {:>9}  {} bytes",
+                other.display_name(),
+                other.kind,
+                other.payload(ilff).len()
+            );
+        }
+    }
+    Ok(())
+}
+
+fn info(file: &Path, debug_log: &mut Vec<String>) -> anyhow::Result<()> {
+    let (resource, images) = load(file, debug_log)?;
+    let actual_size = std::fs::metadata(file)?.len();
+    println!("file:          {}", file.display());
+    let ilff = match resource {
+        ResourceFile::Ilff(ilff) => ilff,
+        ResourceFile::Tex(tex) => {
+            println!("container:     LOOP (loose .tex), {} bytes", actual_size);
+            println!("version:       {}", tex.header.version);
+            println!("mode:          {}", tex.header.mode);
+            println!("resolution:    {}x{}", tex.header.width, tex.header.height);
+            println!("mip levels:    {}", tex.header.mip_count);
+            println!("sub-images:    {}", tex.header.image_count);
+            println!("textures:      {}", images.len());
+            return Ok(());
+        }
+    };
+    println!("resource type: {}", fourcc(ilff.resource_type));
+    println!("filesize:      {} (actual {})", ilff.header.filesize, actual_size);
+    println!("alignment:     {}", ilff.header.alignment);
+    println!("reserve:       {}", ilff.header.reserve);
+    println!("chunks:        {}", ilff.chunks.len());
+
+    let mut tags: Vec<(u32, usize)> = Vec::new();
+    for chunk in &ilff.chunks {
+        match tags.iter_mut().find(|(tag, _)| *tag == chunk.tag) {
+            Some((_, count)) => *count += 1,
+            None => tags.push((chunk.tag, 1)),
+        }
+    }
+    for (tag, count) in tags {
+        println!("  {}: {}", fourcc(tag), count);
+    }
+    let mut kinds: Vec<(ResourceKind, usize)> = Vec::new();
+    for other in resource::resources(&ilff) {
+        match kinds.iter_mut().find(|(kind, _)| *kind == other.kind) {
+            Some((_, count)) => *count += 1,
+            None => kinds.push((other.kind, 1)),
+        }
+    }
+    println!("resources:     {}", kinds.iter().map(|(_, count)| count).sum::<usize>());
+    for (kind, count) in kinds {
+        println!("  {}: {}", kind, count);
+    }
+    println!("textures:      {}", images.len());
+    Ok(())
+}
+
+fn extract(file: &Path, output: &Path, frames: bool, debug_log: &mut Vec<String>) -> anyhow::Result<()> {
+    let (_, images) = load(file, debug_log)?;
+    std::fs::create_dir_all(output)?;
+    let mut written = export::export_all(&images, output, debug_log)?;
+    if frames {
+        for (i, image) in images.iter().enumerate() {
+            written.extend(export::export_frames(image, i, output, debug_log)?);
+        }
+    }
+    for path in &written {
+        println!("{}", path.display());
+    }
+    Ok(())
+}
+
+fn validate(file: &Path, debug_log: &mut Vec<String>) -> anyhow::Result<()> {
+    let (resource, images) = load(file, debug_log)?;
+    let ResourceFile::Ilff(ilff) = resource else {
+        // A loose .tex holds one texture, and images() fails when it can't be decoded.
+        println!("{}: ok, {} textures", file.display(), images.len());
+        return Ok(());
+    };
+    if ilff.resource_type != RES_TYPE_IRES {
+        // Only texture archives have their textures decoded.
+        let resources = resource::resources(&ilff).len();
+        println!("{}: ok, {} chunks, {} resources", file.display(), ilff.chunks.len(), resources);
+        return Ok(());
+    }
+    let failed: Vec<String> = resource::other_resources(&ilff, &images)
+        .iter()
+        .filter(|body| body.kind == ResourceKind::Texture)
+        .map(|body| {
+            // images() has already failed on textures too small for a sub-header.
+            let body_type = TextureHeader::read(body.payload(&ilff)).body_type;
+            match PixelFormat::from_body_type(body_type) {
+                Some(format) => format!("{}: too short for a {} texture", body.display_name(), format),
+                None => format!("{}: unknown body_type {}", body.display_name(), body_type),
+            }
+        })
+        .collect();
+    if !failed.is_empty() {
+        anyhow::bail!(
+            "{} of {} textures could not be decoded, run with --verbose for details:\n  {}",
+            failed.len(),
+            failed.len() + images.len(),
+            failed.join("\n  ")
+        );
+    }
+    println!("{}: ok, {} chunks, {} textures", file.display(), ilff.chunks.len(), images.len());
+    Ok(())
+}
+
+fn pack(
+    dir: &Path,
+    output: &Path,
+    format: Option<PixelFormat>,
+    manifest: Option<&Path>,
+    mipmaps: bool,
+) -> anyhow::Result<()> {
+    let mut manifest = match manifest {
+        Some(path) => Manifest::load(path)?,
+        None => Manifest::default(),
+    };
+    manifest.format = format.or(manifest.format);
+    manifest.mipmaps |= mipmaps;
+
+    let ilff = pack::pack_dir(dir, &manifest, PixelFormat::Argb1555)?;
+    ilff.save(output)?;
+    println!("{}: packed {} textures", output.display(), ilff.chunks.len() / 2);
+    Ok(())
+}
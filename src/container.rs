@@ -0,0 +1,530 @@
+//! The ILFF container: file header and the chunk walk, and telling it
+//! apart from loose `.tex` files.
+
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::ops::ControlFlow;
+use std::path::Path;
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use crate::chunk::{fourcc, Chunk, ChunkHeader};
+use crate::error::{IlffError, Result};
+use crate::tex::{TexFile, MAGIC_LOOP};
+use crate::texture::{self, ImageResource};
+
+/// Magic number at the start of every ILFF file.
+pub const MAGIC_ILFF: u32 = 0x46464C49; // 'ILFF'
+/// Resource type of texture archives.
+pub const RES_TYPE_IRES: u32 = 0x53455249; // 'IRES'
+
+/// The fields between the magic number and the resource type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IlffHeader {
+    /// Total size of the file as recorded in the header.
+    pub filesize: u32,
+    pub alignment: u32,
+    pub reserve: u32,
+}
+
+impl IlffHeader {
+    /// Size of the file header on disk, including magic and resource type.
+    pub const SIZE: u32 = 20;
+}
+
+/// How far [`IlffFile::read_with_progress`] has got.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Progress {
+    pub bytes_read: u64,
+    pub total_bytes: u64,
+    /// Chunks read so far.
+    pub chunks: usize,
+}
+
+impl Progress {
+    /// Fraction of the file read, from 0 to 1.
+    pub fn fraction(&self) -> f32 {
+        if self.total_bytes == 0 {
+            return 0.0;
+        }
+        (self.bytes_read.min(self.total_bytes) as f64 / self.total_bytes as f64) as f32
+    }
+}
+
+/// Every chunk of an ILFF file, in file order and without interpretation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IlffFile {
+    pub header: IlffHeader,
+    pub resource_type: u32,
+    pub chunks: Vec<Chunk>,
+}
+
+impl IlffFile {
+    /// Opens `filename` and reads its chunks, see [`IlffFile::read`].
+    pub fn open<P: AsRef<Path>>(filename: P, debug_log: &mut Vec<String>) -> Result<Self> {
+        let filename = filename.as_ref();
+        debug_log.push(format!("Opening file: {}", filename.display()));
+        let file = File::open(filename).map_err(IlffError::open(filename))?;
+        Self::read(&mut BufReader::new(file), debug_log)
+    }
+
+    /// Reads the header and every chunk, whatever the resource type.
+    ///
+    /// The chunk walk is appended to `debug_log`.
+    pub fn read<R: Read + Seek>(reader: &mut R, debug_log: &mut Vec<String>) -> Result<Self> {
+        Self::read_with_progress(reader, debug_log, |_| ControlFlow::Continue(()))
+    }
+
+    /// Like [`IlffFile::read`], calling `progress` after every chunk.
+    ///
+    /// Reading stops with [`IlffError::Cancelled`] as soon as `progress`
+    /// returns [`ControlFlow::Break`].
+    pub fn read_with_progress<R, F>(
+        reader: &mut R,
+        debug_log: &mut Vec<String>,
+        mut progress: F,
+    ) -> Result<Self>
+    where
+        R: Read + Seek,
+        F: FnMut(Progress) -> ControlFlow<()>,
+    {
+        let file_len = reader.seek(SeekFrom::End(0)).map_err(IlffError::io(0))?;
+        reader.seek(SeekFrom::Start(0)).map_err(IlffError::io(0))?;
+
+        check_header_size(file_len, 4)?;
+        let magic = reader.read_u32::<LittleEndian>().map_err(IlffError::io(0))?;
+        debug_log.push(format!("Read magic number: 0x{:08X}", magic));
+        if magic != MAGIC_ILFF {
+            debug_log.push("Invalid magic number!".to_string());
+            return Err(IlffError::BadMagic { offset: 0, expected: MAGIC_ILFF, actual: magic });
+        }
+        check_header_size(file_len, IlffHeader::SIZE)?;
+
+        let mut fields = [0u32; 4];
+        reader
+            .read_u32_into::<LittleEndian>(&mut fields)
+            .map_err(IlffError::io(4))?;
+        let [filesize, alignment, reserve, resource_type] = fields;
+        debug_log.push(format!("Resource type: 0x{:08X}", resource_type));
+
+        let mut chunks = Vec::new();
+        for chunk_index in 0.. {
+            let offset = reader.stream_position().map_err(IlffError::io(file_len))?;
+            let remaining = file_len.saturating_sub(offset);
+            if remaining < ChunkHeader::SIZE as u64 {
+                if remaining == 0 {
+                    break;
+                }
+                let mut tail = [0u8; ChunkHeader::SIZE as usize];
+                reader
+                    .read_exact(&mut tail[..remaining as usize])
+                    .map_err(IlffError::io(offset))?;
+                return Err(IlffError::TruncatedChunk {
+                    offset,
+                    chunk_index,
+                    tag: u32::from_le_bytes([tail[0], tail[1], tail[2], tail[3]]),
+                    expected: ChunkHeader::SIZE as u64,
+                    actual: remaining,
+                });
+            }
+            let Some(header) = ChunkHeader::read(reader).map_err(IlffError::io(offset))? else {
+                break;
+            };
+            debug_log.push(format!(
+                "Reading chunk type: 0x{:08X} with buffer size: {}",
+                header.chunk_type, header.buffer_size
+            ));
+
+            let chunk_start = offset + ChunkHeader::SIZE as u64;
+            if chunk_start + header.buffer_size as u64 > file_len {
+                return Err(IlffError::TruncatedChunk {
+                    offset,
+                    chunk_index,
+                    tag: header.chunk_type,
+                    expected: header.buffer_size as u64,
+                    actual: file_len - chunk_start,
+                });
+            }
+            if header.alignment == 0 {
+                return Err(IlffError::ZeroAlignment { offset, chunk_index, tag: header.chunk_type });
+            }
+
+            let mut payload = vec![0u8; header.buffer_size as usize];
+            reader.read_exact(&mut payload).map_err(IlffError::io(chunk_start))?;
+            let mut chunk = Chunk {
+                tag: header.chunk_type,
+                alignment: header.alignment,
+                buffer_size: header.buffer_size,
+                chunk_size: header.chunk_size,
+                offset,
+                payload,
+                trailing: Vec::new(),
+            };
+
+            // The file may end before the last chunk's padding does.
+            let trailing = chunk.padding().min(file_len - chunk.end());
+            chunk.trailing = vec![0u8; trailing as usize];
+            reader.read_exact(&mut chunk.trailing).map_err(IlffError::io(chunk.end()))?;
+            chunks.push(chunk);
+
+            let bytes_read = reader.stream_position().map_err(IlffError::io(file_len))?;
+            let update = Progress { bytes_read, total_bytes: file_len, chunks: chunks.len() };
+            if progress(update).is_break() {
+                debug_log.push("Reading cancelled.".to_string());
+                return Err(IlffError::Cancelled { offset: bytes_read });
+            }
+        }
+
+        Ok(Self {
+            header: IlffHeader { filesize, alignment, reserve },
+            resource_type,
+            chunks,
+        })
+    }
+}
+
+impl IlffFile {
+    /// Interprets the chunks as textures, see [`texture::images`].
+    ///
+    /// Fails with [`IlffError::BadResourceType`] unless this is an `IRES`
+    /// file.
+    pub fn images(&self, debug_log: &mut Vec<String>) -> Result<Vec<ImageResource>> {
+        if self.resource_type != RES_TYPE_IRES {
+            debug_log.push("Invalid resource type!".to_string());
+            return Err(IlffError::BadResourceType {
+                offset: 16,
+                expected: RES_TYPE_IRES,
+                actual: self.resource_type,
+            });
+        }
+        texture::images(self, debug_log)
+    }
+
+    /// Writes the file to `filename`, see [`IlffFile::write`].
+    pub fn save<P: AsRef<Path>>(&self, filename: P) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(filename)?);
+        self.write(&mut writer)?;
+        writer.flush()
+    }
+
+    /// Writes the header and every chunk, each followed by its
+    /// [`Chunk::trailing`] bytes.
+    ///
+    /// Header fields are written as stored, except that `buffer_size` is
+    /// always the payload length. A file that was read and not modified is
+    /// written back identically, padding and all; call
+    /// [`IlffFile::update_layout`] first after changing payloads.
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_u32::<LittleEndian>(MAGIC_ILFF)?;
+        writer.write_u32::<LittleEndian>(self.header.filesize)?;
+        writer.write_u32::<LittleEndian>(self.header.alignment)?;
+        writer.write_u32::<LittleEndian>(self.header.reserve)?;
+        writer.write_u32::<LittleEndian>(self.resource_type)?;
+
+        for chunk in &self.chunks {
+            writer.write_u32::<LittleEndian>(chunk.tag)?;
+            writer.write_u32::<LittleEndian>(chunk.payload.len() as u32)?;
+            writer.write_u32::<LittleEndian>(chunk.alignment)?;
+            writer.write_u32::<LittleEndian>(chunk.chunk_size)?;
+            writer.write_all(&chunk.payload)?;
+            writer.write_all(&chunk.trailing)?;
+        }
+        Ok(())
+    }
+
+    /// Recomputes the sizes and offsets that depend on the payloads: every
+    /// chunk's `buffer_size`, `offset`, `chunk_size` and `trailing` padding,
+    /// and the header's `filesize`.
+    ///
+    /// `chunk_size` is the distance from a chunk's header to the next one,
+    /// and 0 for the last chunk. Padding that no longer has the right length
+    /// is replaced by zeros, so every chunk ends on its alignment again.
+    pub fn update_layout(&mut self) {
+        let mut pos = IlffHeader::SIZE as u64;
+        let count = self.chunks.len();
+        for (i, chunk) in self.chunks.iter_mut().enumerate() {
+            chunk.buffer_size = chunk.payload.len() as u32;
+            chunk.offset = pos;
+            let padding = chunk.padding();
+            if chunk.trailing.len() as u64 != padding {
+                chunk.trailing = vec![0u8; padding as usize];
+            }
+            let size = ChunkHeader::SIZE as u64 + chunk.buffer_size as u64 + padding;
+            chunk.chunk_size = if i + 1 == count { 0 } else { size as u32 };
+            pos += size;
+        }
+        self.header.filesize = pos as u32;
+    }
+}
+
+/// An ILFF archive or a loose `.tex` file, told apart by the magic number.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResourceFile {
+    Ilff(IlffFile),
+    Tex(TexFile),
+}
+
+impl ResourceFile {
+    /// Opens `filename` and reads it as whichever container it is.
+    pub fn open<P: AsRef<Path>>(filename: P, debug_log: &mut Vec<String>) -> Result<Self> {
+        Self::open_with_progress(filename, debug_log, |_| ControlFlow::Continue(()))
+    }
+
+    /// Like [`ResourceFile::open`], reporting progress as
+    /// [`IlffFile::read_with_progress`] does.
+    pub fn open_with_progress<P, F>(filename: P, debug_log: &mut Vec<String>, progress: F) -> Result<Self>
+    where
+        P: AsRef<Path>,
+        F: FnMut(Progress) -> ControlFlow<()>,
+    {
+        let filename = filename.as_ref();
+        debug_log.push(format!("Opening file: {}", filename.display()));
+        let file = File::open(filename).map_err(IlffError::open(filename))?;
+        let mut file = Self::read_with_progress(&mut BufReader::new(file), debug_log, progress)?;
+        if let ResourceFile::Tex(tex) = &mut file {
+            tex.name = filename.file_name().map(|name| name.to_string_lossy().into_owned());
+        }
+        Ok(file)
+    }
+
+    /// Reads an ILFF or `LOOP` file from the start of `reader`.
+    ///
+    /// Fails with [`IlffError::UnknownMagic`] when it is neither.
+    pub fn read_with_progress<R, F>(reader: &mut R, debug_log: &mut Vec<String>, progress: F) -> Result<Self>
+    where
+        R: Read + Seek,
+        F: FnMut(Progress) -> ControlFlow<()>,
+    {
+        let file_len = reader.seek(SeekFrom::End(0)).map_err(IlffError::io(0))?;
+        reader.seek(SeekFrom::Start(0)).map_err(IlffError::io(0))?;
+        check_header_size(file_len, 4)?;
+        let magic = reader.read_u32::<LittleEndian>().map_err(IlffError::io(0))?;
+        reader.seek(SeekFrom::Start(0)).map_err(IlffError::io(0))?;
+        match magic {
+            MAGIC_ILFF => IlffFile::read_with_progress(reader, debug_log, progress).map(ResourceFile::Ilff),
+            MAGIC_LOOP => TexFile::read_with_progress(reader, debug_log, progress).map(ResourceFile::Tex),
+            actual => {
+                debug_log.push(format!("Unknown magic number: 0x{:08X}", actual));
+                Err(IlffError::UnknownMagic { actual })
+            }
+        }
+    }
+
+    /// Decodes the textures, see [`IlffFile::images`] and [`TexFile::images`].
+    ///
+    /// Archives of another resource type than `IRES` hold no textures, only
+    /// [`resources`](crate::resource::resources), so they give an empty list
+    /// instead of [`IlffError::BadResourceType`].
+    pub fn images(&self, debug_log: &mut Vec<String>) -> Result<Vec<ImageResource>> {
+        match self {
+            ResourceFile::Ilff(ilff) if ilff.resource_type != RES_TYPE_IRES => {
+                debug_log.push(format!("Resource type '{}' holds no textures.", fourcc(ilff.resource_type)));
+                Ok(Vec::new())
+            }
+            ResourceFile::Ilff(ilff) => ilff.images(debug_log),
+            ResourceFile::Tex(tex) => tex.images(debug_log),
+        }
+    }
+
+    /// Position in the file that [`ImageResource::pixel_offset`] of `image`
+    /// counts from.
+    pub fn pixel_base(&self, image: &ImageResource) -> u64 {
+        match self {
+            ResourceFile::Ilff(ilff) => ilff.chunks[image.chunk_index].payload_offset(),
+            ResourceFile::Tex(_) => 0,
+        }
+    }
+
+    pub fn as_ilff(&self) -> Option<&IlffFile> {
+        match self {
+            ResourceFile::Ilff(ilff) => Some(ilff),
+            ResourceFile::Tex(_) => None,
+        }
+    }
+
+    pub fn as_ilff_mut(&mut self) -> Option<&mut IlffFile> {
+        match self {
+            ResourceFile::Ilff(ilff) => Some(ilff),
+            ResourceFile::Tex(_) => None,
+        }
+    }
+}
+
+/// Opens `filename` and reads every texture in it, see [`read_ilff`].
+pub fn read_ilff_file<P: AsRef<Path>>(
+    filename: P,
+    debug_log: &mut Vec<String>,
+) -> Result<Vec<ImageResource>> {
+    IlffFile::open(filename, debug_log)?.images(debug_log)
+}
+
+/// Reads every texture from an `IRES` container.
+///
+/// Progress and skipped chunks are appended to `debug_log`.
+pub fn read_ilff<R: Read + Seek>(
+    reader: &mut R,
+    debug_log: &mut Vec<String>,
+) -> Result<Vec<ImageResource>> {
+    IlffFile::read(reader, debug_log)?.images(debug_log)
+}
+
+/// Fails with [`IlffError::TruncatedFile`] when a file of `file_len` bytes
+/// can't hold a header of `size` bytes.
+pub(crate) fn check_header_size(file_len: u64, size: u32) -> Result<()> {
+    if file_len < size as u64 {
+        return Err(IlffError::TruncatedFile { expected: size as u64, actual: file_len });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::chunk::CHUNK_TYPE_BODY;
+
+    /// Tag, payload, alignment and the bytes after the payload.
+    type RawChunk<'a> = (&'a [u8; 4], &'a [u8], u32, &'a [u8]);
+
+    /// An `IRES` file made of `chunks`.
+    fn ilff_bytes(chunks: &[RawChunk]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(b"ILFF");
+        bytes.extend_from_slice(&0u32.to_le_bytes());
+        bytes.extend_from_slice(&4u32.to_le_bytes());
+        bytes.extend_from_slice(&0u32.to_le_bytes());
+        bytes.extend_from_slice(b"IRES");
+        for &(tag, payload, alignment, trailing) in chunks {
+            bytes.extend_from_slice(tag);
+            bytes.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(&alignment.to_le_bytes());
+            bytes.extend_from_slice(&0u32.to_le_bytes());
+            bytes.extend_from_slice(payload);
+            bytes.extend_from_slice(trailing);
+        }
+        let filesize = bytes.len() as u32;
+        bytes[4..8].copy_from_slice(&filesize.to_le_bytes());
+        bytes
+    }
+
+    fn read(bytes: &[u8]) -> Result<IlffFile> {
+        IlffFile::read(&mut Cursor::new(bytes), &mut Vec::new())
+    }
+
+    fn read_resource(bytes: &[u8]) -> Result<ResourceFile> {
+        ResourceFile::read_with_progress(&mut Cursor::new(bytes), &mut Vec::new(), |_| ControlFlow::Continue(()))
+    }
+
+    fn round_trip(bytes: &[u8]) -> Vec<u8> {
+        let mut written = Vec::new();
+        read(bytes).unwrap().write(&mut written).unwrap();
+        written
+    }
+
+    #[test]
+    fn write_keeps_non_zero_padding() {
+        let bytes = ilff_bytes(&[(b"NAME", b"a.tex", 4, b"\xAA\xBB\xCC"), (b"BODY", b"xyz", 4, b"\x01")]);
+        assert_eq!(round_trip(&bytes), bytes);
+    }
+
+    #[test]
+    fn write_keeps_unpadded_last_chunk() {
+        let bytes = ilff_bytes(&[(b"NAME", b"a.tex", 4, b"\0\0\0"), (b"BODY", b"xyz", 4, b"")]);
+        assert_eq!(round_trip(&bytes), bytes);
+    }
+
+    #[test]
+    fn update_layout_pads_resized_chunks_with_zeros() {
+        let bytes = ilff_bytes(&[(b"NAME", b"a.tex", 4, b"\xAA\xBB\xCC"), (b"BODY", b"xyz", 4, b"")]);
+        let mut file = read(&bytes).unwrap();
+        file.chunks[0].payload = b"ab.tex".to_vec();
+        file.update_layout();
+        assert_eq!(file.chunks[0].trailing, [0, 0]);
+        assert_eq!(file.chunks[1].trailing, [0]);
+        let mut written = Vec::new();
+        file.write(&mut written).unwrap();
+        assert_eq!(written.len() as u32, file.header.filesize);
+    }
+
+    #[test]
+    fn bad_magic() {
+        let mut bytes = ilff_bytes(&[]);
+        bytes[..4].copy_from_slice(b"FFLI");
+        let err = read(&bytes).unwrap_err();
+        assert!(matches!(err, IlffError::BadMagic { offset: 0, expected: MAGIC_ILFF, .. }), "{err:?}");
+    }
+
+    #[test]
+    fn missing_file() {
+        let path = std::env::temp_dir().join("resviewer_missing_file.res");
+        let err = ResourceFile::open(&path, &mut Vec::new()).unwrap_err();
+        let IlffError::Open { path: err_path, .. } = &err else {
+            panic!("{err:?}");
+        };
+        assert_eq!(err_path, &path);
+        assert!(err.to_string().contains("resviewer_missing_file.res"), "{err}");
+    }
+
+    #[test]
+    fn file_shorter_than_magic() {
+        let err = read_resource(b"IL").unwrap_err();
+        assert!(matches!(err, IlffError::TruncatedFile { expected: 4, actual: 2 }), "{err:?}");
+    }
+
+    #[test]
+    fn file_shorter_than_header() {
+        let err = read(&ilff_bytes(&[])[..12]).unwrap_err();
+        assert!(matches!(err, IlffError::TruncatedFile { expected: 20, actual: 12 }), "{err:?}");
+    }
+
+    #[test]
+    fn truncated_chunk_header() {
+        let mut bytes = ilff_bytes(&[(b"NAME", b"a.tex", 4, b"\0\0\0")]);
+        bytes.extend_from_slice(b"BODY\x08");
+        let err = read(&bytes).unwrap_err();
+        assert_eq!(err.offset(), 44);
+        let IlffError::TruncatedChunk { chunk_index, tag, expected, actual, .. } = err else {
+            panic!("{err:?}");
+        };
+        assert_eq!((chunk_index, tag, expected, actual), (1, CHUNK_TYPE_BODY, 16, 5));
+    }
+
+    #[test]
+    fn truncated_chunk_payload() {
+        let mut bytes = ilff_bytes(&[(b"NAME", b"a.tex", 4, b"\0\0\0"), (b"BODY", b"0123456789", 4, b"")]);
+        bytes.truncate(bytes.len() - 4);
+        let err = read(&bytes).unwrap_err();
+        let IlffError::TruncatedChunk { offset, chunk_index, tag, expected, actual } = err else {
+            panic!("{err:?}");
+        };
+        assert_eq!((offset, chunk_index, tag, expected, actual), (44, 1, CHUNK_TYPE_BODY, 10, 6));
+    }
+
+    #[test]
+    fn zero_alignment() {
+        let bytes = ilff_bytes(&[(b"NAME", b"a.tex", 4, b"\0\0\0"), (b"BODY", b"xyz", 0, b"")]);
+        let err = read(&bytes).unwrap_err();
+        assert!(matches!(err, IlffError::ZeroAlignment { offset: 44, chunk_index: 1, .. }), "{err:?}");
+    }
+
+    #[test]
+    fn body_smaller_than_sub_header() {
+        let bytes = ilff_bytes(&[(b"NAME", b"a.tex", 4, b"\0\0\0"), (b"BODY", &[0; 8], 4, b"")]);
+        let err = read(&bytes).unwrap().images(&mut Vec::new()).unwrap_err();
+        let IlffError::ChunkTooSmall { offset, chunk_index, tag, expected, actual } = err else {
+            panic!("{err:?}");
+        };
+        assert_eq!((offset, chunk_index, tag, expected, actual), (44, 1, CHUNK_TYPE_BODY, 32, 8));
+    }
+
+    #[test]
+    fn other_resource_types_open_without_textures() {
+        let mut bytes = ilff_bytes(&[(b"NAME", b"a.mef", 4, b"\0\0\0"), (b"BODY", b"mesh", 4, b"")]);
+        bytes[16..20].copy_from_slice(b"IMSH");
+        let file = read_resource(&bytes).unwrap();
+        assert!(file.images(&mut Vec::new()).unwrap().is_empty());
+        let err = file.as_ilff().unwrap().images(&mut Vec::new()).unwrap_err();
+        assert!(matches!(err, IlffError::BadResourceType { offset: 16, .. }), "{err:?}");
+    }
+}
@@ -0,0 +1,103 @@
+//! Errors returned while reading ILFF and loose `.tex` files.
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+
+use crate::chunk::fourcc;
+
+/// Result type of the ILFF readers.
+pub type Result<T> = std::result::Result<T, IlffError>;
+
+/// Why an ILFF file could not be read.
+///
+/// Offsets are byte positions from the start of the file; chunk indices count
+/// every chunk from 0, whatever its type.
+#[derive(Debug, Error)]
+pub enum IlffError {
+    #[error("cannot open {}: {source}", path.display())]
+    Open {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+
+    #[error("I/O error at offset {offset:#x}: {source}")]
+    Io {
+        offset: u64,
+        #[source]
+        source: io::Error,
+    },
+
+    #[error("invalid magic number at offset {offset:#x}: expected '{}', found '{}' ({actual:#010X})", fourcc(*expected), fourcc(*actual))]
+    BadMagic { offset: u64, expected: u32, actual: u32 },
+
+    #[error("unknown file type: magic number '{}' ({actual:#010X}) is neither 'ILFF' nor 'LOOP'", fourcc(*actual))]
+    UnknownMagic { actual: u32 },
+
+    #[error("unsupported resource type at offset {offset:#x}: expected '{}', found '{}' ({actual:#010X})", fourcc(*expected), fourcc(*actual))]
+    BadResourceType { offset: u64, expected: u32, actual: u32 },
+
+    #[error("file is truncated: it is {actual} bytes, shorter than its {expected}-byte header")]
+    TruncatedFile { expected: u64, actual: u64 },
+
+    #[error("chunk {chunk_index} ('{}') at offset {offset:#x} is truncated: needs {expected} bytes, only {actual} left", fourcc(*tag))]
+    TruncatedChunk {
+        offset: u64,
+        chunk_index: usize,
+        tag: u32,
+        expected: u64,
+        actual: u64,
+    },
+
+    #[error("chunk {chunk_index} ('{}') at offset {offset:#x} is {actual} bytes, smaller than its {expected}-byte header", fourcc(*tag))]
+    ChunkTooSmall {
+        offset: u64,
+        chunk_index: usize,
+        tag: u32,
+        expected: u32,
+        actual: u32,
+    },
+
+    #[error("chunk {chunk_index} ('{}') at offset {offset:#x} has an alignment of 0", fourcc(*tag))]
+    ZeroAlignment { offset: u64, chunk_index: usize, tag: u32 },
+
+    #[error("unsupported texture mode {mode} at offset {offset:#x}")]
+    UnsupportedMode { offset: u64, mode: u32 },
+
+    #[error("texture data at offset {offset:#x} is truncated: needs {expected} bytes, only {actual} left")]
+    TruncatedTexture { offset: u64, expected: u64, actual: u64 },
+
+    #[error("reading was cancelled at offset {offset:#x}")]
+    Cancelled { offset: u64 },
+}
+
+impl IlffError {
+    /// Wraps the error of opening `path`, for use with `map_err`.
+    pub(crate) fn open(path: &Path) -> impl FnOnce(io::Error) -> IlffError + '_ {
+        move |source| IlffError::Open { path: path.to_path_buf(), source }
+    }
+
+    /// Wraps an I/O error that happened at `offset`, for use with `map_err`.
+    pub(crate) fn io(offset: u64) -> impl FnOnce(io::Error) -> IlffError {
+        move |source| IlffError::Io { offset, source }
+    }
+
+    /// Byte offset in the file the error refers to.
+    pub fn offset(&self) -> u64 {
+        match *self {
+            IlffError::Io { offset, .. }
+            | IlffError::BadMagic { offset, .. }
+            | IlffError::BadResourceType { offset, .. }
+            | IlffError::TruncatedChunk { offset, .. }
+            | IlffError::ChunkTooSmall { offset, .. }
+            | IlffError::ZeroAlignment { offset, .. }
+            | IlffError::UnsupportedMode { offset, .. }
+            | IlffError::TruncatedTexture { offset, .. }
+            | IlffError::Cancelled { offset } => offset,
+            IlffError::TruncatedFile { actual, .. } => actual,
+            IlffError::Open { .. } | IlffError::UnknownMagic { .. } => 0,
+        }
+    }
+}
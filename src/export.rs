@@ -0,0 +1,181 @@
+//! Writing textures out as PNG files.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use image::{ImageResult, RgbaImage};
+
+use crate::texture::ImageResource;
+
+/// Name shown for image `index`: its `NAME` chunk, or `Image {index}` when it
+/// has none.
+pub fn display_name(image: &ImageResource, index: usize) -> String {
+    image.name.clone().unwrap_or_else(|| format!("Image {}", index))
+}
+
+/// PNG file name for image `index`, with `.tex` replaced by `.png` and path
+/// separators and other characters that are invalid in file names replaced
+/// by `_`.
+pub fn png_file_name(image: &ImageResource, index: usize) -> String {
+    let name = display_name(image, index);
+    let stem = match name.len().checked_sub(4) {
+        Some(split) if name.is_char_boundary(split) && name[split..].eq_ignore_ascii_case(".tex") => {
+            &name[..split]
+        }
+        _ => &name,
+    };
+    let stem: String = stem
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    format!("{}.png", stem)
+}
+
+/// PNG file name for sub-image `frame` of image `index`: the image's
+/// [`png_file_name`] with the frame number appended, e.g. `font_012.png`.
+pub fn frame_file_name(image: &ImageResource, index: usize, frame: usize) -> String {
+    let name = png_file_name(image, index);
+    let width = image.sub_images.len().saturating_sub(1).to_string().len().max(3);
+    format!("{}_{:0width$}.png", name.trim_end_matches(".png"), frame, width = width)
+}
+
+/// [`png_file_name`] for every image, with ` (2)`, ` (3)`, ... appended to
+/// names that are already taken, ignoring case.
+pub fn png_file_names(images: &[ImageResource]) -> Vec<String> {
+    let mut taken = HashSet::new();
+    images
+        .iter()
+        .enumerate()
+        .map(|(i, image)| {
+            let name = png_file_name(image, i);
+            if taken.insert(name.to_lowercase()) {
+                return name;
+            }
+            let stem = name.trim_end_matches(".png");
+            (2..)
+                .map(|n| format!("{} ({}).png", stem, n))
+                .find(|candidate| taken.insert(candidate.to_lowercase()))
+                .unwrap()
+        })
+        .collect()
+}
+
+/// Writes the full-size level of `image` to `path` as an RGBA PNG.
+pub fn save_png<P: AsRef<Path>>(image: &ImageResource, path: P) -> ImageResult<()> {
+    let pixels = RgbaImage::from_raw(image.width as u32, image.height as u32, image.data.clone())
+        .expect("RGBA data matches the texture size");
+    pixels.save_with_format(path, image::ImageFormat::Png)
+}
+
+/// Writes sub-image `frame` of `image` to `path` as an RGBA PNG.
+///
+/// # Panics
+///
+/// Panics if there is no such sub-image or it doesn't fit in the image, see
+/// [`ImageResource::sub_image`].
+pub fn save_frame_png<P: AsRef<Path>>(image: &ImageResource, frame: usize, path: P) -> ImageResult<()> {
+    let pixels = image.sub_image(frame).expect("sub-image within the texture");
+    pixels.save_with_format(path, image::ImageFormat::Png)
+}
+
+/// Writes every sub-image of `image` that fits in it into `dir` under the
+/// names from [`frame_file_name`]; returns the paths written.
+pub fn export_frames<P: AsRef<Path>>(
+    image: &ImageResource,
+    index: usize,
+    dir: P,
+    debug_log: &mut Vec<String>,
+) -> ImageResult<Vec<PathBuf>> {
+    let mut written = Vec::new();
+    for (frame, sub) in image.sub_images.iter().enumerate() {
+        if !sub.fits(image.width, image.height) {
+            debug_log.push(format!("Skipped sub-image {} outside the texture", frame));
+            continue;
+        }
+        let path = dir.as_ref().join(frame_file_name(image, index, frame));
+        save_frame_png(image, frame, &path)?;
+        debug_log.push(format!("Exported {}", path.display()));
+        written.push(path);
+    }
+    Ok(written)
+}
+
+/// Writes every image into `dir` under the names from [`png_file_names`].
+///
+/// Stops at the first image that can't be written; returns the paths written.
+pub fn export_all<P: AsRef<Path>>(
+    images: &[ImageResource],
+    dir: P,
+    debug_log: &mut Vec<String>,
+) -> ImageResult<Vec<PathBuf>> {
+    let mut written = Vec::new();
+    for (image, name) in images.iter().zip(png_file_names(images)) {
+        let path = dir.as_ref().join(name);
+        save_png(image, &path)?;
+        debug_log.push(format!("Exported {}", path.display()));
+        written.push(path);
+    }
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::PixelFormat;
+    use crate::texture::TextureHeader;
+
+    fn named(name: Option<&str>) -> ImageResource {
+        ImageResource {
+            name: name.map(str::to_string),
+            chunk_index: 0,
+            width: 1,
+            height: 1,
+            format: PixelFormat::Rgb565,
+            format_guessed: false,
+            raw: vec![0; 2],
+            data: vec![0, 0, 0, 255],
+            mipmaps: Vec::new(),
+            header: TextureHeader::default(),
+            data_offset: 0,
+            sub_images: Vec::new(),
+        }
+    }
+
+    fn file_names(names: &[Option<&str>]) -> Vec<String> {
+        let images: Vec<_> = names.iter().map(|&name| named(name)).collect();
+        png_file_names(&images)
+    }
+
+    #[test]
+    fn tex_extension_is_replaced() {
+        assert_eq!(png_file_name(&named(Some("rock.tex")), 0), "rock.png");
+        assert_eq!(png_file_name(&named(Some("ROCK.TEX")), 0), "ROCK.png");
+        assert_eq!(png_file_name(&named(Some("rock.tga")), 0), "rock.tga.png");
+        assert_eq!(png_file_name(&named(Some(".tex")), 0), ".png");
+        assert_eq!(png_file_name(&named(Some("é.tex")), 0), "é.png");
+        assert_eq!(png_file_name(&named(None), 7), "Image 7.png");
+    }
+
+    #[test]
+    fn invalid_characters_are_replaced() {
+        let name = png_file_name(&named(Some("textures\\sky/a:b*c?\"<>|\t.tex")), 0);
+        assert_eq!(name, "textures_sky_a_b_c______.png");
+    }
+
+    #[test]
+    fn taken_names_get_a_number_ignoring_case() {
+        let names = ["a.tex", "A.tex", "a.TEX", "b.tex", "a (2).tex"].map(Some);
+        let names = file_names(&names);
+        assert_eq!(names, ["a.png", "A (2).png", "a (3).png", "b.png", "a (2) (2).png"]);
+    }
+
+    #[test]
+    fn unnamed_images_are_numbered_by_index() {
+        let names = file_names(&[None, Some("Image 0.tex"), None]);
+        assert_eq!(names, ["Image 0.png", "Image 0 (2).png", "Image 2.png"]);
+    }
+}
@@ -0,0 +1,323 @@
+//! Pixel formats used by IGI textures and their conversion to RGBA8.
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer};
+
+/// `body_type` of 32-bit textures whose header doesn't record the channel
+/// order.
+pub const BODY_TYPE_32BIT: u32 = 0;
+
+/// Layout of the pixels in a texture `BODY` chunk.
+///
+/// The 16-bit formats are little-endian words with the channels listed from
+/// the most significant bit down; the 24 and 32-bit formats are named after
+/// their byte order in the file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PixelFormat {
+    Rgb565,
+    Argb1555,
+    Argb4444,
+    Bgr888,
+    Bgra8888,
+    Argb8888,
+    Rgba8888,
+}
+
+impl PixelFormat {
+    pub const ALL: [PixelFormat; 7] = [
+        PixelFormat::Rgb565,
+        PixelFormat::Argb1555,
+        PixelFormat::Argb4444,
+        PixelFormat::Bgr888,
+        PixelFormat::Bgra8888,
+        PixelFormat::Argb8888,
+        PixelFormat::Rgba8888,
+    ];
+
+    /// 32-bit formats, in the order they are offered as overrides.
+    pub const ALL_32BIT: [PixelFormat; 3] = [
+        PixelFormat::Rgba8888,
+        PixelFormat::Bgra8888,
+        PixelFormat::Argb8888,
+    ];
+
+    /// Maps the `body_type` field of a `BODY` sub-header to a pixel format.
+    ///
+    /// Returns `None` for values that have not been seen in IGI 1 or IGI 2
+    /// archives yet. [`BODY_TYPE_32BIT`] maps to BGRA, but its channel order
+    /// is better found with [`PixelFormat::guess_32bit_order`].
+    pub fn from_body_type(body_type: u32) -> Option<Self> {
+        match body_type {
+            BODY_TYPE_32BIT => Some(PixelFormat::Bgra8888),
+            1 => Some(PixelFormat::Rgb565),
+            2 => Some(PixelFormat::Argb1555),
+            3 => Some(PixelFormat::Bgra8888),
+            4 => Some(PixelFormat::Bgr888),
+            67 => Some(PixelFormat::Argb4444),
+            _ => None,
+        }
+    }
+
+    /// The `body_type` a texture in this format is stored with, the inverse
+    /// of [`PixelFormat::from_body_type`].
+    ///
+    /// ARGB8888 and RGBA8888 have none, IGI stores 32-bit textures as BGRA.
+    pub fn body_type(self) -> Option<u32> {
+        match self {
+            PixelFormat::Rgb565 => Some(1),
+            PixelFormat::Argb1555 => Some(2),
+            PixelFormat::Bgra8888 => Some(3),
+            PixelFormat::Bgr888 => Some(4),
+            PixelFormat::Argb4444 => Some(67),
+            PixelFormat::Argb8888 | PixelFormat::Rgba8888 => None,
+        }
+    }
+
+    pub fn bytes_per_pixel(self) -> usize {
+        match self {
+            PixelFormat::Rgb565 | PixelFormat::Argb1555 | PixelFormat::Argb4444 => 2,
+            PixelFormat::Bgr888 => 3,
+            PixelFormat::Bgra8888 | PixelFormat::Argb8888 | PixelFormat::Rgba8888 => 4,
+        }
+    }
+
+    /// Number of bytes taken by a `width` x `height` image in this format.
+    pub fn image_size(self, width: u16, height: u16) -> usize {
+        width as usize * height as usize * self.bytes_per_pixel()
+    }
+
+    pub fn is_32bit(self) -> bool {
+        self.bytes_per_pixel() == 4
+    }
+
+    /// Guesses the channel order of 32-bit pixels from their contents.
+    ///
+    /// Alpha is mostly fully opaque or fully transparent, so the outer byte
+    /// with the most `0x00`/`0xFF` values is taken to be alpha. RGBA and BGRA
+    /// can't be told apart this way, and D3D-era textures are BGRA.
+    pub fn guess_32bit_order(raw: &[u8]) -> Self {
+        let mut first = 0usize;
+        let mut last = 0usize;
+        for px in raw.chunks_exact(4) {
+            first += matches!(px[0], 0x00 | 0xFF) as usize;
+            last += matches!(px[3], 0x00 | 0xFF) as usize;
+        }
+        if first > last {
+            PixelFormat::Argb8888
+        } else {
+            PixelFormat::Bgra8888
+        }
+    }
+
+    pub fn has_alpha(self) -> bool {
+        !matches!(self, PixelFormat::Rgb565 | PixelFormat::Bgr888)
+    }
+
+    /// Converts `raw` pixels in this format to unmultiplied RGBA8.
+    ///
+    /// Trailing bytes that don't make up a whole pixel are ignored.
+    pub fn to_rgba8(self, raw: &[u8]) -> Vec<u8> {
+        let bpp = self.bytes_per_pixel();
+        let mut rgba = Vec::with_capacity(raw.len() / bpp * 4);
+        for px in raw.chunks_exact(bpp) {
+            rgba.extend_from_slice(&self.decode_pixel(px));
+        }
+        rgba
+    }
+
+    /// Converts a single pixel of `bytes_per_pixel()` bytes to RGBA8.
+    pub fn decode_pixel(self, px: &[u8]) -> [u8; 4] {
+        match self {
+            PixelFormat::Rgb565 => {
+                let v = u16::from_le_bytes([px[0], px[1]]);
+                [expand5(v >> 11), expand6(v >> 5), expand5(v), 0xFF]
+            }
+            PixelFormat::Argb1555 => {
+                let v = u16::from_le_bytes([px[0], px[1]]);
+                let a = if v & 0x8000 != 0 { 0xFF } else { 0 };
+                [expand5(v >> 10), expand5(v >> 5), expand5(v), a]
+            }
+            PixelFormat::Argb4444 => {
+                let v = u16::from_le_bytes([px[0], px[1]]);
+                [expand4(v >> 8), expand4(v >> 4), expand4(v), expand4(v >> 12)]
+            }
+            PixelFormat::Bgr888 => [px[2], px[1], px[0], 0xFF],
+            PixelFormat::Bgra8888 => [px[2], px[1], px[0], px[3]],
+            PixelFormat::Argb8888 => [px[1], px[2], px[3], px[0]],
+            PixelFormat::Rgba8888 => [px[0], px[1], px[2], px[3]],
+        }
+    }
+
+    /// Converts unmultiplied RGBA8 pixels to this format.
+    ///
+    /// Channels are rounded to the nearest value the format can hold; alpha
+    /// is dropped by formats without it.
+    pub fn from_rgba8(self, rgba: &[u8]) -> Vec<u8> {
+        let mut raw = Vec::with_capacity(rgba.len() / 4 * self.bytes_per_pixel());
+        for px in rgba.chunks_exact(4) {
+            self.encode_pixel([px[0], px[1], px[2], px[3]], &mut raw);
+        }
+        raw
+    }
+
+    /// Appends one RGBA8 pixel in this format to `out`.
+    pub fn encode_pixel(self, [r, g, b, a]: [u8; 4], out: &mut Vec<u8>) {
+        match self {
+            PixelFormat::Rgb565 => {
+                let v = (reduce(r, 5) << 11) | (reduce(g, 6) << 5) | reduce(b, 5);
+                out.extend_from_slice(&v.to_le_bytes());
+            }
+            PixelFormat::Argb1555 => {
+                let a = if a >= 0x80 { 0x8000 } else { 0 };
+                let v = a | (reduce(r, 5) << 10) | (reduce(g, 5) << 5) | reduce(b, 5);
+                out.extend_from_slice(&v.to_le_bytes());
+            }
+            PixelFormat::Argb4444 => {
+                let v = (reduce(a, 4) << 12) | (reduce(r, 4) << 8) | (reduce(g, 4) << 4) | reduce(b, 4);
+                out.extend_from_slice(&v.to_le_bytes());
+            }
+            PixelFormat::Bgr888 => out.extend_from_slice(&[b, g, r]),
+            PixelFormat::Bgra8888 => out.extend_from_slice(&[b, g, r, a]),
+            PixelFormat::Argb8888 => out.extend_from_slice(&[a, r, g, b]),
+            PixelFormat::Rgba8888 => out.extend_from_slice(&[r, g, b, a]),
+        }
+    }
+}
+
+impl fmt::Display for PixelFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            PixelFormat::Rgb565 => "RGB565",
+            PixelFormat::Argb1555 => "ARGB1555",
+            PixelFormat::Argb4444 => "ARGB4444",
+            PixelFormat::Bgr888 => "BGR888",
+            PixelFormat::Bgra8888 => "BGRA8888",
+            PixelFormat::Argb8888 => "ARGB8888",
+            PixelFormat::Rgba8888 => "RGBA8888",
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for PixelFormat {
+    type Err = String;
+
+    /// Parses a format name such as `argb1555`, ignoring case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        PixelFormat::ALL
+            .into_iter()
+            .find(|format| format.to_string().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                let names: Vec<String> =
+                    PixelFormat::ALL.iter().map(|f| f.to_string().to_lowercase()).collect();
+                format!("unknown pixel format '{}', expected one of {}", s, names.join(", "))
+            })
+    }
+}
+
+/// Formats are written by name, e.g. `"argb1555"`.
+impl<'de> Deserialize<'de> for PixelFormat {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// Rounds an 8-bit channel to `bits` bits.
+fn reduce(v: u8, bits: u32) -> u16 {
+    let max = (1u16 << bits) - 1;
+    (v as u16 * max + 127) / 255
+}
+
+fn expand4(v: u16) -> u8 {
+    (v as u8 & 0x0F) * 0x11
+}
+
+fn expand5(v: u16) -> u8 {
+    let v = v as u8 & 0x1F;
+    (v << 3) | (v >> 2)
+}
+
+fn expand6(v: u16) -> u8 {
+    let v = v as u8 & 0x3F;
+    (v << 2) | (v >> 4)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode_word(format: PixelFormat, word: u16) -> [u8; 4] {
+        format.decode_pixel(&word.to_le_bytes())
+    }
+
+    fn encode_word(format: PixelFormat, rgba: [u8; 4]) -> u16 {
+        let mut out = Vec::new();
+        format.encode_pixel(rgba, &mut out);
+        u16::from_le_bytes([out[0], out[1]])
+    }
+
+    #[test]
+    fn decodes_16bit_words() {
+        assert_eq!(decode_word(PixelFormat::Rgb565, 0xF800), [0xFF, 0, 0, 0xFF]);
+        assert_eq!(decode_word(PixelFormat::Rgb565, 0x07E0), [0, 0xFF, 0, 0xFF]);
+        assert_eq!(decode_word(PixelFormat::Rgb565, 0x001F), [0, 0, 0xFF, 0xFF]);
+        assert_eq!(decode_word(PixelFormat::Rgb565, 0x8410), [0x84, 0x82, 0x84, 0xFF]);
+
+        assert_eq!(decode_word(PixelFormat::Argb1555, 0x7C00), [0xFF, 0, 0, 0]);
+        assert_eq!(decode_word(PixelFormat::Argb1555, 0x83E0), [0, 0xFF, 0, 0xFF]);
+        assert_eq!(decode_word(PixelFormat::Argb1555, 0x8010), [0, 0, 0x84, 0xFF]);
+
+        assert_eq!(decode_word(PixelFormat::Argb4444, 0xF0F0), [0, 0xFF, 0, 0xFF]);
+        assert_eq!(decode_word(PixelFormat::Argb4444, 0x1234), [0x22, 0x33, 0x44, 0x11]);
+    }
+
+    #[test]
+    fn decodes_byte_orders() {
+        let px = [1, 2, 3, 4];
+        assert_eq!(PixelFormat::Bgr888.decode_pixel(&px[..3]), [3, 2, 1, 0xFF]);
+        assert_eq!(PixelFormat::Bgra8888.decode_pixel(&px), [3, 2, 1, 4]);
+        assert_eq!(PixelFormat::Argb8888.decode_pixel(&px), [2, 3, 4, 1]);
+        assert_eq!(PixelFormat::Rgba8888.decode_pixel(&px), [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn encode_rounds_to_nearest() {
+        // 127 and 128 sit either side of the middle of 5-bit value 15.5.
+        assert_eq!(encode_word(PixelFormat::Rgb565, [127, 0, 0, 0xFF]), 15 << 11);
+        assert_eq!(encode_word(PixelFormat::Rgb565, [128, 0, 0, 0xFF]), 16 << 11);
+        assert_eq!(encode_word(PixelFormat::Rgb565, [0xFF, 0xFF, 0xFF, 0]), 0xFFFF);
+        assert_eq!(encode_word(PixelFormat::Argb4444, [0x08, 0x09, 0x77, 0xFF]), 0xF017);
+    }
+
+    #[test]
+    fn argb1555_alpha_threshold() {
+        assert_eq!(encode_word(PixelFormat::Argb1555, [0, 0, 0, 0x7F]), 0);
+        assert_eq!(encode_word(PixelFormat::Argb1555, [0, 0, 0, 0x80]), 0x8000);
+    }
+
+    #[test]
+    fn every_16bit_word_survives_decode_and_encode() {
+        for format in [PixelFormat::Rgb565, PixelFormat::Argb1555, PixelFormat::Argb4444] {
+            for word in 0..=u16::MAX {
+                assert_eq!(encode_word(format, decode_word(format, word)), word, "{format} {word:#06x}");
+            }
+        }
+    }
+
+    #[test]
+    fn byte_formats_survive_encode_and_decode() {
+        let rgba = [0x12, 0x34, 0x56, 0x78];
+        let formats = [PixelFormat::Bgr888, PixelFormat::Bgra8888, PixelFormat::Argb8888, PixelFormat::Rgba8888];
+        for format in formats {
+            let mut raw = Vec::new();
+            format.encode_pixel(rgba, &mut raw);
+            assert_eq!(raw.len(), format.bytes_per_pixel());
+            let expected = if format.has_alpha() { rgba } else { [0x12, 0x34, 0x56, 0xFF] };
+            assert_eq!(format.decode_pixel(&raw), expected, "{format}");
+        }
+    }
+}
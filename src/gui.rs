@@ -0,0 +1,991 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use eframe::egui;
+use egui::FontDefinitions;
+use image::imageops::{self, FilterType};
+use image::RgbaImage;
+use resviewer::export::{self, display_name};
+use resviewer::texture::{replace_texture, BODY_SUBHEADER_FIELDS};
+use resviewer::{IlffError, IlffFile, ImageResource, PixelFormat, ResourceFile};
+use rfd::FileDialog;
+
+mod filter;
+mod loader;
+mod resources;
+mod settings;
+mod statistics;
+mod structure;
+mod thumbnails;
+mod view;
+
+use filter::ImageFilter;
+use loader::Loader;
+use resources::ResourceViewer;
+use settings::{RecentFiles, Settings};
+use structure::StructureView;
+use thumbnails::Thumbnails;
+use view::ImageView;
+
+#[derive(Default)]
+pub struct MyApp {
+    /// The open file with every chunk, kept for saving.
+    file: Option<ResourceFile>,
+    images: Vec<ImageResource>,
+    selected_index: Option<usize>,
+    selected_level: usize,
+    /// Sub-image of the selected atlas picked in the frame list.
+    selected_frame: Option<usize>,
+    /// Uploaded textures by image index and mip level.
+    textures: HashMap<(usize, usize), egui::TextureHandle>,
+    file_path: Option<String>,
+    error_message: Option<String>,
+    show_debug_console: bool,
+    debug_log: Vec<String>,
+    /// A PNG waiting for confirmation because its size doesn't match.
+    pending_replace: Option<PendingReplace>,
+    view: ImageView,
+    /// Texel of the selected image and level under the mouse.
+    hovered_pixel: Option<(u16, u16)>,
+    /// The file being read in the background, if any.
+    loader: Option<Loader>,
+    /// Show the image list as a grid of thumbnails instead of names.
+    show_thumbnails: bool,
+    thumbnails: Thumbnails,
+    filter: ImageFilter,
+    /// Text, sounds and everything else that isn't a texture.
+    resources: ResourceViewer,
+    recent: RecentFiles,
+    show_structure: bool,
+    structure: StructureView,
+    show_statistics: bool,
+    /// Files opened next to this one, each shown in a window of its own.
+    windows: Vec<(egui::ViewportId, MyApp)>,
+    /// Windows opened so far, for unique viewport ids.
+    windows_opened: usize,
+}
+
+/// Something picked from a context menu in the image list.
+enum ListAction {
+    Replace(usize),
+    ExportFrames(usize),
+    ExportFrame(usize, usize),
+    SaveResource(usize),
+}
+
+struct PendingReplace {
+    index: usize,
+    pixels: RgbaImage,
+}
+
+impl MyApp {
+    /// Creates the app, opening `files` right away (see [`Self::open_files`]).
+    pub fn new(cc: &eframe::CreationContext<'_>, files: Vec<PathBuf>) -> Self {
+        let mut fonts = FontDefinitions::default();
+        fonts.font_data.insert(
+            "Inter".to_owned(),
+            egui::FontData::from_static(include_bytes!("fonts/Inter-Regular.ttf")),
+        );
+        fonts.families
+            .entry(egui::FontFamily::Proportional)
+            .or_default()
+            .insert(0, "Inter".to_owned());
+        fonts.families
+            .entry(egui::FontFamily::Monospace)
+            .or_default()
+            .push("Inter".to_owned());
+        cc.egui_ctx.set_fonts(fonts);
+
+        let mut app = Self::default();
+        let settings = cc.storage.and_then(|storage| eframe::get_value::<Settings>(storage, eframe::APP_KEY));
+        if let Some(settings) = settings {
+            settings.apply(&mut app);
+        }
+        app.open_files(&cc.egui_ctx, files);
+        app
+    }
+
+    /// Replaces the open file. Everything that refers to the old file's
+    /// images (selection, uploaded textures, a pending replacement) is reset.
+    fn set_file(&mut self, file: ResourceFile, images: Vec<ImageResource>) {
+        self.resources.set_file(file.as_ilff(), &images);
+        self.file = Some(file);
+        self.images = images;
+        self.selected_index = None;
+        self.selected_level = 0;
+        self.selected_frame = None;
+        self.textures.clear();
+        self.thumbnails.clear();
+        self.structure.reset();
+        self.pending_replace = None;
+        self.hovered_pixel = None;
+        self.view.reset_pan();
+    }
+
+    /// Starts reading `path` on a worker thread, cancelling any file that is
+    /// still loading. The open file stays up until the new one is ready.
+    fn open_file(&mut self, ctx: &egui::Context, path: &Path) {
+        if let Some(loader) = self.loader.take() {
+            loader.cancel();
+        }
+        self.loader = Some(Loader::spawn(path, ctx));
+    }
+
+    /// Opens the first of `paths` here and each of the others in a new
+    /// window of its own, see [`Self::show_windows`]. Where windows can't be
+    /// opened (the viewports are embedded), each file replaces the last.
+    fn open_files(&mut self, ctx: &egui::Context, paths: Vec<PathBuf>) {
+        let mut paths = paths.into_iter();
+        if let Some(first) = paths.next() {
+            self.open_file(ctx, &first);
+        }
+        for path in paths {
+            if ctx.embed_viewports() {
+                self.debug_log.push(format!("Can't open a new window, opening {} here", path.display()));
+                self.open_file(ctx, &path);
+                continue;
+            }
+            let mut window = MyApp::default();
+            Settings::from_app(self).apply(&mut window);
+            window.open_file(ctx, &path);
+            self.windows_opened += 1;
+            // Windows open windows of their own, so the ID is made unique
+            // under this one's rather than by the counter alone.
+            let id = egui::ViewportId(ctx.viewport_id().0.with(("file_window", self.windows_opened)));
+            self.windows.push((id, window));
+            self.debug_log.push(format!("Opened {} in a new window", path.display()));
+        }
+    }
+
+    /// Shows the files opened in windows of their own, in this process so
+    /// that only the main window saves settings. The windows share its list
+    /// of recent files; closed ones are dropped.
+    fn show_windows(&mut self, ctx: &egui::Context) {
+        let recent = &mut self.recent;
+        self.windows.retain_mut(|(id, window)| {
+            let title = window.file_path.clone().unwrap_or_else(|| "IGI TEX Viewer".to_string());
+            let builder = egui::ViewportBuilder::default().with_title(title);
+            std::mem::swap(&mut window.recent, recent);
+            let open = ctx.show_viewport_immediate(*id, builder, |ctx, _| {
+                window.ui(ctx);
+                !ctx.input(|i| i.viewport().close_requested())
+            });
+            std::mem::swap(&mut window.recent, recent);
+            open
+        });
+    }
+
+    /// Opens `.res` and `.tex` files dropped onto the window, and shows a
+    /// hint while files are dragged over it.
+    fn handle_dropped_files(&mut self, ctx: &egui::Context) {
+        let (hovering, dropped) =
+            ctx.input(|i| (!i.raw.hovered_files.is_empty(), i.raw.dropped_files.clone()));
+        if hovering {
+            let screen = ctx.screen_rect();
+            let layer = egui::LayerId::new(egui::Order::Foreground, egui::Id::new("drop_hint"));
+            let painter = ctx.layer_painter(layer);
+            painter.rect_filled(screen, 0.0, egui::Color32::from_black_alpha(160));
+            painter.text(
+                screen.center(),
+                egui::Align2::CENTER_CENTER,
+                "Drop .res or .tex files to open them",
+                egui::FontId::proportional(20.0),
+                egui::Color32::WHITE,
+            );
+        }
+
+        let mut paths = Vec::new();
+        for path in dropped.into_iter().filter_map(|file| file.path) {
+            let extension = path.extension().and_then(|e| e.to_str()).map(str::to_ascii_lowercase);
+            if matches!(extension.as_deref(), Some("res" | "tex")) {
+                paths.push(path);
+            } else {
+                self.debug_log.push(format!("Ignored dropped file: {}", path.display()));
+            }
+        }
+        if !paths.is_empty() {
+            self.open_files(ctx, paths);
+        }
+    }
+
+    /// Picks up the background loader's progress and, once it is done, its file.
+    fn poll_loader(&mut self) {
+        let Some(loader) = &mut self.loader else {
+            return;
+        };
+        let Some((loaded, mut log)) = loader.poll() else {
+            return;
+        };
+        let path = loader.path.clone();
+        self.loader = None;
+        self.debug_log.append(&mut log);
+        match loaded {
+            Ok((file, images)) => {
+                self.set_file(file, images);
+                self.recent.add(&path);
+                self.file_path = Some(path.to_string_lossy().to_string());
+                self.error_message = None;
+                self.debug_log.push("File successfully loaded.".to_string());
+            }
+            Err(IlffError::Cancelled { .. }) => {
+                self.debug_log.push("Loading cancelled.".to_string());
+            }
+            Err(e) => {
+                self.error_message = Some(format!("Failed to read file: {}", e));
+                self.debug_log.push(format!("Failed to read file: {}", e));
+            }
+        }
+    }
+
+    /// The open file when it is an ILFF archive; loose `.tex` files can't
+    /// be saved or have textures replaced.
+    fn ilff(&self) -> Option<&IlffFile> {
+        self.file.as_ref().and_then(ResourceFile::as_ilff)
+    }
+
+    fn save_as(&mut self) {
+        if self.ilff().is_none() {
+            return;
+        }
+        if let Some(path) = FileDialog::new()
+            .add_filter("Resource Files", &["res"])
+            .set_directory(self.recent.dialog_directory())
+            .save_file()
+        {
+            self.recent.set_directory(&path);
+            self.save_to(path.to_string_lossy().to_string());
+        }
+    }
+
+    fn save_to(&mut self, path: String) {
+        let Some(file) = self.ilff() else {
+            return;
+        };
+        match file.save(&path) {
+            Ok(()) => {
+                self.debug_log.push(format!("Saved file: {}", path));
+                self.file_path = Some(path);
+                self.error_message = None;
+            }
+            Err(e) => {
+                self.error_message = Some(format!("Failed to save file: {}", e));
+                self.debug_log.push(format!("Failed to save file: {}", e));
+            }
+        }
+    }
+
+    fn export_selected(&mut self) {
+        let Some(index) = self.selected_index else {
+            return;
+        };
+        let names = export::png_file_names(&self.images);
+        let Some(path) = FileDialog::new()
+            .add_filter("PNG Images", &["png"])
+            .set_directory(self.recent.dialog_directory())
+            .set_file_name(&names[index])
+            .save_file()
+        else {
+            return;
+        };
+        self.recent.set_directory(&path);
+        match export::save_png(&self.images[index], &path) {
+            Ok(()) => {
+                self.debug_log.push(format!("Exported {}", path.display()));
+                self.error_message = None;
+            }
+            Err(e) => {
+                self.error_message = Some(format!("Failed to export image: {}", e));
+                self.debug_log.push(format!("Failed to export image: {}", e));
+            }
+        }
+    }
+
+    fn export_frame(&mut self, index: usize, frame: usize) {
+        let image = &self.images[index];
+        let Some(path) = FileDialog::new()
+            .add_filter("PNG Images", &["png"])
+            .set_directory(self.recent.dialog_directory())
+            .set_file_name(export::frame_file_name(image, index, frame))
+            .save_file()
+        else {
+            return;
+        };
+        self.recent.set_directory(&path);
+        match export::save_frame_png(image, frame, &path) {
+            Ok(()) => {
+                self.debug_log.push(format!("Exported {}", path.display()));
+                self.error_message = None;
+            }
+            Err(e) => {
+                self.error_message = Some(format!("Failed to export frame: {}", e));
+                self.debug_log.push(format!("Failed to export frame: {}", e));
+            }
+        }
+    }
+
+    fn export_frames(&mut self, index: usize) {
+        let Some(dir) = FileDialog::new().set_directory(self.recent.dialog_directory()).pick_folder() else {
+            return;
+        };
+        self.recent.set_directory(&dir);
+        match export::export_frames(&self.images[index], index, &dir, &mut self.debug_log) {
+            Ok(written) => {
+                self.debug_log.push(format!("Exported {} frames to {}", written.len(), dir.display()));
+                self.error_message = None;
+            }
+            Err(e) => {
+                self.error_message = Some(format!("Failed to export frames: {}", e));
+                self.debug_log.push(format!("Failed to export frames: {}", e));
+            }
+        }
+    }
+
+    /// Saves the payload of resource `index` as it is in the archive.
+    fn save_resource(&mut self, index: usize) {
+        let Some(file) = self.ilff() else {
+            return;
+        };
+        let resource = self.resources.get(index);
+        let payload = resource.payload(file).to_vec();
+        let name = resource.display_name().replace(['/', '\\', ':'], "_");
+        let Some(path) = FileDialog::new()
+            .set_directory(self.recent.dialog_directory())
+            .set_file_name(name)
+            .save_file()
+        else {
+            return;
+        };
+        self.recent.set_directory(&path);
+        match std::fs::write(&path, payload) {
+            Ok(()) => {
+                self.debug_log.push(format!("Saved {}", path.display()));
+                self.error_message = None;
+            }
+            Err(e) => {
+                self.error_message = Some(format!("Failed to save resource: {}", e));
+                self.debug_log.push(format!("Failed to save resource: {}", e));
+            }
+        }
+    }
+
+    fn export_all(&mut self) {
+        let Some(dir) = FileDialog::new().set_directory(self.recent.dialog_directory()).pick_folder() else {
+            return;
+        };
+        self.recent.set_directory(&dir);
+        match export::export_all(&self.images, &dir, &mut self.debug_log) {
+            Ok(written) => {
+                self.debug_log.push(format!("Exported {} images to {}", written.len(), dir.display()));
+                self.error_message = None;
+            }
+            Err(e) => {
+                self.error_message = Some(format!("Failed to export images: {}", e));
+                self.debug_log.push(format!("Failed to export images: {}", e));
+            }
+        }
+    }
+
+    /// Asks for a PNG to replace image `index` with, and replaces it right
+    /// away when the size matches.
+    fn start_replace(&mut self, index: usize) {
+        let Some(path) = FileDialog::new()
+            .add_filter("PNG Images", &["png"])
+            .set_directory(self.recent.dialog_directory())
+            .pick_file()
+        else {
+            return;
+        };
+        self.recent.set_directory(&path);
+        let pixels = match image::open(&path) {
+            Ok(png) => png.to_rgba8(),
+            Err(e) => {
+                self.error_message = Some(format!("Failed to read PNG: {}", e));
+                self.debug_log.push(format!("Failed to read PNG: {}", e));
+                return;
+            }
+        };
+        if pixels.width() > u16::MAX as u32 || pixels.height() > u16::MAX as u32 {
+            self.error_message = Some(format!(
+                "PNG is {}x{}, textures can be at most {}x{}.",
+                pixels.width(),
+                pixels.height(),
+                u16::MAX,
+                u16::MAX
+            ));
+            return;
+        }
+
+        let image = &self.images[index];
+        if pixels.dimensions() == (image.width as u32, image.height as u32) {
+            self.replace(index, &pixels);
+        } else {
+            self.pending_replace = Some(PendingReplace { index, pixels });
+        }
+    }
+
+    fn replace(&mut self, index: usize, pixels: &RgbaImage) {
+        let Some(file) = self.file.as_mut().and_then(ResourceFile::as_ilff_mut) else {
+            return;
+        };
+        match replace_texture(file, &self.images[index], pixels, &mut self.debug_log) {
+            Some(image) => {
+                self.images[index] = image;
+                self.textures.retain(|&(i, _), _| i != index);
+                self.thumbnails.invalidate(index);
+                if self.selected_index == Some(index) {
+                    self.selected_level = 0;
+                }
+                self.error_message = None;
+            }
+            None => {
+                self.error_message = Some("Failed to read back the replaced texture.".to_string());
+            }
+        }
+    }
+
+    fn show_replace_dialog(&mut self, ctx: &egui::Context) {
+        let Some(pending) = &self.pending_replace else {
+            return;
+        };
+        let image = &self.images[pending.index];
+        let (width, height) = (image.width as u32, image.height as u32);
+        let mut choice = None;
+        let mut cancel = false;
+        egui::Window::new("Replace Texture")
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "The PNG is {}x{} but the texture is {}x{}.",
+                    pending.pixels.width(),
+                    pending.pixels.height(),
+                    width,
+                    height
+                ));
+                ui.horizontal(|ui| {
+                    if ui.button(format!("Resize to {}x{}", width, height)).clicked() {
+                        choice = Some(true);
+                    }
+                    if ui.button("Keep PNG size").clicked() {
+                        choice = Some(false);
+                    }
+                    if ui.button("Cancel").clicked() {
+                        cancel = true;
+                    }
+                });
+            });
+
+        if cancel {
+            self.pending_replace = None;
+        } else if let Some(resize) = choice {
+            let pending = self.pending_replace.take().unwrap();
+            let pixels = if resize {
+                imageops::resize(&pending.pixels, width, height, FilterType::Lanczos3)
+            } else {
+                pending.pixels
+            };
+            self.replace(pending.index, &pixels);
+        }
+    }
+
+    /// Status line with the position, decoded colour, raw value and file
+    /// offset of the hovered pixel.
+    fn show_pixel_info(&self, ui: &mut egui::Ui) {
+        let level = self.selected_level;
+        let hovered = match (self.selected_index, self.hovered_pixel, &self.file) {
+            (Some(index), Some((x, y)), Some(file)) => {
+                let (width, height, _) = self.images[index].level(level);
+                (x < width && y < height).then_some((&self.images[index], x, y, file))
+            }
+            _ => None,
+        };
+        let Some((image, x, y, file)) = hovered else {
+            ui.label("Hover the image to inspect pixels.");
+            return;
+        };
+        let raw = image.raw_pixel(level, x, y);
+        let [r, g, b, a] = image.format.decode_pixel(raw);
+        let raw_text = match raw {
+            [lo, hi] => format!("0x{:04X}", u16::from_le_bytes([*lo, *hi])),
+            bytes => bytes.iter().map(|b| format!("{:02X}", b)).collect::<Vec<_>>().join(" "),
+        };
+        let offset = file.pixel_base(image) + image.pixel_offset(level, x, y) as u64;
+        ui.monospace(format!(
+            "x: {} y: {} | RGBA: {}, {}, {}, {} | {}: {} | Offset: 0x{:X}",
+            x, y, r, g, b, a, image.format, raw_text, offset
+        ));
+    }
+
+    /// Side panel with the selected image's size, format and every field of
+    /// its `BODY` sub-header.
+    fn show_properties(&self, ctx: &egui::Context) {
+        let Some(image) = self.selected_index.map(|index| &self.images[index]) else {
+            return;
+        };
+        egui::SidePanel::right("properties").resizable(true).show(ctx, |ui| {
+            ui.heading("Properties");
+            egui::Grid::new("properties_grid").num_columns(2).striped(true).show(ui, |ui| {
+                let format = if image.format_guessed {
+                    format!("{} (guessed)", image.format)
+                } else {
+                    image.format.to_string()
+                };
+                let rows = [
+                    ("Name", image.name.clone().unwrap_or_default()),
+                    ("Chunk", image.chunk_index.to_string()),
+                    ("Resolution", format!("{}x{}", image.width, image.height)),
+                    ("Format", format),
+                    ("Mip levels", image.level_count().to_string()),
+                    ("Size", format!("{} bytes", image.raw.len())),
+                ];
+                for (name, value) in rows {
+                    ui.label(name);
+                    ui.label(value);
+                    ui.end_row();
+                }
+            });
+            ui.separator();
+            ui.strong("BODY header");
+            egui::Grid::new("properties_header").num_columns(2).striped(true).show(ui, |ui| {
+                for ((name, _, _), value) in BODY_SUBHEADER_FIELDS.iter().zip(image.header.values()) {
+                    ui.label(*name);
+                    ui.monospace(format!("{} ({:#x})", value, value));
+                    ui.end_row();
+                }
+            });
+        });
+    }
+
+    /// Draws the whole window; split from `update` so tests can drive it
+    /// without an `eframe::Frame`.
+    fn ui(&mut self, ctx: &egui::Context) {
+        self.poll_loader();
+        self.handle_dropped_files(ctx);
+        self.show_windows(ctx);
+
+        egui::TopBottomPanel::top("menu_bar").show(ctx, |ui| {
+            egui::menu::bar(ui, |ui| {
+                ui.menu_button("File", |ui| {
+                    if ui.button("Open").clicked() {
+                        if let Some(path) = FileDialog::new()
+                            .add_filter("Resource Files", &["res", "tex"])
+                            .set_directory(self.recent.dialog_directory())
+                            .pick_file()
+                        {
+                            self.open_file(ctx, &path);
+                        }
+                        ui.close_menu();
+                    }
+                    ui.add_enabled_ui(!self.recent.files.is_empty(), |ui| {
+                        ui.menu_button("Recent", |ui| {
+                            let mut open = None;
+                            for path in &self.recent.files {
+                                let button = egui::Button::new(path.display().to_string());
+                                if ui.add_enabled(path.exists(), button).clicked() {
+                                    open = Some(path.clone());
+                                }
+                            }
+                            ui.separator();
+                            if ui.button("Clear Recent").clicked() {
+                                self.recent.files.clear();
+                                ui.close_menu();
+                            }
+                            if let Some(path) = open {
+                                self.open_file(ctx, &path);
+                                ui.close_menu();
+                            }
+                        });
+                    });
+                    let save_path = self.ilff().and(self.file_path.clone());
+                    if ui.add_enabled(save_path.is_some(), egui::Button::new("Save")).clicked() {
+                        if let Some(path) = save_path {
+                            self.save_to(path);
+                        }
+                        ui.close_menu();
+                    }
+                    if ui.add_enabled(self.ilff().is_some(), egui::Button::new("Save As…")).clicked() {
+                        self.save_as();
+                        ui.close_menu();
+                    }
+                    ui.separator();
+                    if ui
+                        .add_enabled(self.selected_index.is_some(), egui::Button::new("Export Selected…"))
+                        .clicked()
+                    {
+                        self.export_selected();
+                        ui.close_menu();
+                    }
+                    if ui.add_enabled(!self.images.is_empty(), egui::Button::new("Export All…")).clicked() {
+                        self.export_all();
+                        ui.close_menu();
+                    }
+                });
+                ui.menu_button("Debug", |ui| {
+                    if ui.checkbox(&mut self.show_debug_console, "Debug Console").clicked() {
+                        ui.close_menu();
+                    }
+                    if ui.checkbox(&mut self.show_structure, "Structure").clicked() {
+                        ui.close_menu();
+                    }
+                    if ui.checkbox(&mut self.show_statistics, "Header Statistics").clicked() {
+                        ui.close_menu();
+                    }
+                });
+            });
+        });
+
+        egui::TopBottomPanel::bottom("status_bar").show(ctx, |ui| {
+            if let Some(loader) = &self.loader {
+                let progress = loader.progress;
+                ui.horizontal(|ui| {
+                    if ui.button("Cancel").clicked() {
+                        loader.cancel();
+                    }
+                    let text = format!(
+                        "Loading {}: {} / {} bytes, {} chunks",
+                        loader.path.display(),
+                        progress.bytes_read,
+                        progress.total_bytes,
+                        progress.chunks
+                    );
+                    ui.add(egui::ProgressBar::new(progress.fraction()).text(text));
+                });
+            } else {
+                self.show_pixel_info(ui);
+            }
+        });
+
+        egui::SidePanel::left("image_list").resizable(true).show(ctx, |ui| {
+            let mut action = None;
+            if !self.resources.is_empty() {
+                egui::TopBottomPanel::bottom("resource_list").resizable(true).show_inside(ui, |ui| {
+                    ui.heading("Other Resources");
+                    let rows = egui::ScrollArea::vertical()
+                        .auto_shrink([false, true])
+                        .show(ui, |ui| self.resources.list(ui))
+                        .inner;
+                    for (i, response) in rows {
+                        if response.clicked() {
+                            if let Some(file) = self.file.as_ref().and_then(ResourceFile::as_ilff) {
+                                self.resources.select(i, file);
+                            }
+                            self.selected_index = None;
+                            self.selected_frame = None;
+                            self.hovered_pixel = None;
+                        }
+                        response.context_menu(|ui| {
+                            if ui.button("Save Raw…").clicked() {
+                                action = Some(ListAction::SaveResource(i));
+                                ui.close_menu();
+                            }
+                        });
+                    }
+                });
+            }
+            ui.heading("Images");
+            ui.horizontal(|ui| {
+                ui.selectable_value(&mut self.show_thumbnails, false, "List");
+                ui.selectable_value(&mut self.show_thumbnails, true, "Grid");
+            });
+            if self.show_thumbnails {
+                self.thumbnails.size_slider(ui);
+            }
+            self.filter.ui(ui, &self.images);
+            let shown = self.filter.apply(&self.images);
+            if self.filter.is_active() {
+                ui.label(format!("{} of {} images", shown.len(), self.images.len()));
+            }
+            ui.separator();
+            // Image index, frame of an atlas (if the row is one) and the row.
+            let rows: Vec<(usize, Option<usize>, egui::Response)> = egui::ScrollArea::vertical()
+                .auto_shrink([false, true])
+                .show(ui, |ui| {
+                    if self.show_thumbnails {
+                        let cells = self.thumbnails.grid(ui, &self.images, &shown, self.selected_index);
+                        return cells.into_iter().map(|(i, response)| (i, None, response)).collect();
+                    }
+                    let mut rows = Vec::new();
+                    for &i in &shown {
+                        let image = &self.images[i];
+                        let selected = self.selected_index == Some(i);
+                        let response = ui.selectable_label(selected, display_name(image, i));
+                        rows.push((i, None, response));
+                        if selected && !image.sub_images.is_empty() {
+                            ui.indent(("frames", i), |ui| {
+                                for (frame, sub) in image.sub_images.iter().enumerate() {
+                                    let label = format!(
+                                        "Frame {}: {}x{} at {},{}",
+                                        frame, sub.width, sub.height, sub.x, sub.y
+                                    );
+                                    let selected = self.selected_frame == Some(frame);
+                                    let response = ui.selectable_label(selected, label);
+                                    rows.push((i, Some(frame), response));
+                                }
+                            });
+                        }
+                    }
+                    rows
+                })
+                .inner;
+            let can_replace = self.ilff().is_some();
+            for (i, frame, response) in rows {
+                if response.clicked() {
+                    if self.selected_index != Some(i) {
+                        self.selected_level = 0;
+                        self.view.reset_pan();
+                    }
+                    self.selected_index = Some(i);
+                    self.selected_frame = frame;
+                    self.resources.deselect();
+                }
+                let image = &self.images[i];
+                response.context_menu(|ui| match frame {
+                    None => {
+                        if ui.add_enabled(can_replace, egui::Button::new("Replace…")).clicked() {
+                            action = Some(ListAction::Replace(i));
+                            ui.close_menu();
+                        }
+                        let has_frames = !image.sub_images.is_empty();
+                        if ui.add_enabled(has_frames, egui::Button::new("Export Frames…")).clicked() {
+                            action = Some(ListAction::ExportFrames(i));
+                            ui.close_menu();
+                        }
+                    }
+                    Some(frame) => {
+                        let fits = image.sub_images[frame].fits(image.width, image.height);
+                        if ui.add_enabled(fits, egui::Button::new("Export Frame…")).clicked() {
+                            action = Some(ListAction::ExportFrame(i, frame));
+                            ui.close_menu();
+                        }
+                    }
+                });
+            }
+            match action {
+                Some(ListAction::Replace(index)) => self.start_replace(index),
+                Some(ListAction::ExportFrames(index)) => self.export_frames(index),
+                Some(ListAction::ExportFrame(index, frame)) => self.export_frame(index, frame),
+                Some(ListAction::SaveResource(index)) => self.save_resource(index),
+                None => {}
+            }
+        });
+
+        self.show_replace_dialog(ctx);
+        self.show_properties(ctx);
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            if let Some(index) = self.selected_index {
+                let image = &mut self.images[index];
+                if image.format.is_32bit() {
+                    let mut format = image.format;
+                    ui.horizontal(|ui| {
+                        ui.label("Treat as:");
+                        for candidate in PixelFormat::ALL_32BIT {
+                            ui.selectable_value(&mut format, candidate, candidate.to_string());
+                        }
+                        if image.format_guessed {
+                            ui.label("(guessed, not in header)");
+                        }
+                    });
+                    if format != image.format {
+                        image.set_format(format);
+                        self.textures.retain(|&(i, _), _| i != index);
+                        self.thumbnails.invalidate(index);
+                    }
+                }
+                if image.level_count() > 1 {
+                    ui.horizontal(|ui| {
+                        ui.label("Mip level:");
+                        if ui.add_enabled(self.selected_level > 0, egui::Button::new("<")).clicked() {
+                            self.selected_level -= 1;
+                        }
+                        ui.label(format!("{} / {}", self.selected_level, image.level_count() - 1));
+                        if ui
+                            .add_enabled(self.selected_level + 1 < image.level_count(), egui::Button::new(">"))
+                            .clicked()
+                        {
+                            self.selected_level += 1;
+                        }
+                    });
+                }
+                if self.view.display_toolbar(ui) {
+                    self.textures.clear();
+                }
+                let level = self.selected_level;
+                let (width, height, data) = image.level(level);
+                let texture = self.textures.entry((index, level)).or_insert_with(|| {
+                    let color_image = egui::ColorImage::from_rgba_unmultiplied(
+                        [width as usize, height as usize],
+                        &self.view.channels.apply(data),
+                    );
+                    ctx.load_texture(
+                        format!("image_{}_{}", index, level),
+                        color_image,
+                        view::TEXTURE_OPTIONS,
+                    )
+                });
+                ui.label(format!(
+                    "Resolution: {}x{} | Format: {} | Size: {} bytes",
+                    width,
+                    height,
+                    image.format,
+                    image.format.image_size(width, height)
+                ));
+                self.view.toolbar(ui);
+                if !image.sub_images.is_empty() {
+                    ui.horizontal(|ui| {
+                        ui.label(format!("{} frames", image.sub_images.len()));
+                        ui.checkbox(&mut self.view.outlines, "Show outlines");
+                    });
+                }
+                let (response, rect) = self.view.show(ui, texture);
+                if self.view.outlines && !image.sub_images.is_empty() {
+                    // Frames are in full-size texels whatever level is shown.
+                    let size = (image.width, image.height);
+                    view::paint_frames(ui, response.rect, rect, size, &image.sub_images, self.selected_frame);
+                }
+                let hovered = response
+                    .hover_pos()
+                    .and_then(|pos| view::texel_at(pos, rect, width, height));
+                if hovered != self.hovered_pixel {
+                    self.hovered_pixel = hovered;
+                    ctx.request_repaint();
+                }
+            } else if self.resources.selected().is_some() {
+                if let Some(file) = self.file.as_ref().and_then(ResourceFile::as_ilff) {
+                    self.resources.show(ui, file);
+                }
+            } else {
+                ui.label("Select an image or resource from the list.");
+            }
+        });
+
+        if self.show_statistics {
+            egui::Window::new("Header Statistics")
+                .resizable(true)
+                .default_size([500.0, 400.0])
+                .open(&mut self.show_statistics)
+                .show(ctx, |ui| statistics::show(ui, &self.images));
+        }
+
+        if self.show_structure {
+            egui::Window::new("Structure")
+                .resizable(true)
+                .default_size([800.0, 500.0])
+                .open(&mut self.show_structure)
+                .show(ctx, |ui| match self.file.as_ref().and_then(ResourceFile::as_ilff) {
+                    Some(file) => self.structure.show(ui, file),
+                    None => {
+                        ui.label("Open a .res file to see its chunks.");
+                    }
+                });
+        }
+
+        if self.show_debug_console {
+            egui::Window::new("Debug Console")
+                .resizable(true)
+                .scroll([true, true])  // scropllability
+                .default_size([500.0, 300.0])
+                .open(&mut self.show_debug_console)
+                .show(ctx, |ui| {
+                    ui.label("Debug Output:");
+                    egui::ScrollArea::vertical().show(ui, |ui| {
+                        for log in &self.debug_log {
+                            ui.monospace(log);
+                        }
+                    });
+                    if let Some(error) = &self.error_message {
+                        ui.monospace(format!("Error: {}", error));
+                    }
+                });
+        }
+    }
+}
+
+impl eframe::App for MyApp {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.ui(ctx);
+    }
+
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, eframe::APP_KEY, &Settings::from_app(self));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use resviewer::pack::{self, PackEntry};
+
+    /// Packs one opaque BGRA texture per size into a file in the temp dir.
+    fn write_res(name: &str, sizes: &[(u32, u32)]) -> PathBuf {
+        let entries: Vec<PackEntry> = sizes
+            .iter()
+            .enumerate()
+            .map(|(i, &(width, height))| PackEntry {
+                name: format!("{}_{}.tex", name, i),
+                pixels: RgbaImage::from_pixel(width, height, image::Rgba([i as u8, 0, 0, 255])),
+                format: PixelFormat::Bgra8888,
+                mipmaps: false,
+            })
+            .collect();
+        let path = std::env::temp_dir().join(format!("resviewer_{}_{}.res", std::process::id(), name));
+        pack::pack(&entries).unwrap().save(&path).unwrap();
+        path
+    }
+
+    /// Opens `path` and draws frames until the background loader is done.
+    fn open_and_wait(app: &mut MyApp, ctx: &egui::Context, path: &Path) {
+        app.open_file(ctx, path);
+        let start = std::time::Instant::now();
+        while app.loader.is_some() {
+            assert!(start.elapsed() < std::time::Duration::from_secs(10), "loading timed out");
+            std::thread::sleep(std::time::Duration::from_millis(1));
+            let _ = ctx.run(Default::default(), |ctx| app.ui(ctx));
+        }
+    }
+
+    #[test]
+    fn opening_second_file_resets_selection_and_textures() {
+        let first = write_res("first", &[(4, 4), (8, 8), (16, 16)]);
+        let second = write_res("second", &[(2, 2)]);
+        let ctx = egui::Context::default();
+        let mut app = MyApp::default();
+
+        open_and_wait(&mut app, &ctx, &first);
+        app.selected_index = Some(2);
+        let _ = ctx.run(Default::default(), |ctx| app.ui(ctx));
+        assert!(app.textures.contains_key(&(2, 0)));
+
+        open_and_wait(&mut app, &ctx, &second);
+        assert_eq!(app.error_message, None);
+        assert_eq!(app.images.len(), 1);
+        assert_eq!(app.selected_index, None);
+        assert!(app.textures.is_empty());
+
+        // Drawing with the old selection used to index past the new images.
+        let _ = ctx.run(Default::default(), |ctx| app.ui(ctx));
+        app.selected_index = Some(0);
+        let _ = ctx.run(Default::default(), |ctx| app.ui(ctx));
+        assert_eq!(app.textures[&(0, 0)].size(), [2, 2]);
+
+        std::fs::remove_file(first).unwrap();
+        std::fs::remove_file(second).unwrap();
+    }
+
+    #[test]
+    fn extra_files_open_in_windows_only_when_viewports_are_not_embedded() {
+        let paths: Vec<PathBuf> = ["a.res", "b.res", "c.res"].iter().map(PathBuf::from).collect();
+        let ctx = egui::Context::default();
+        let mut app = MyApp::default();
+        app.open_files(&ctx, paths.clone());
+        assert!(app.windows.is_empty());
+        assert!(app.loader.is_some());
+
+        ctx.set_embed_viewports(false);
+        app.open_files(&ctx, paths);
+        let ids: Vec<egui::ViewportId> = app.windows.iter().map(|(id, _)| *id).collect();
+        assert_eq!(ids.len(), 2);
+        assert_ne!(ids[0], ids[1]);
+        assert!(!ids.contains(&ctx.viewport_id()));
+    }
+}
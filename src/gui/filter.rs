@@ -0,0 +1,267 @@
+//! Name search, filters and sort order for the image list.
+
+use std::cmp::Ordering;
+
+use eframe::egui;
+use glob::Pattern;
+use resviewer::export::display_name;
+use resviewer::{ImageResource, PixelFormat};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SortBy {
+    #[default]
+    FileOrder,
+    Name,
+    /// Width times height of the top level.
+    Size,
+    /// Raw bytes of the top level.
+    Bytes,
+}
+
+impl SortBy {
+    const ALL: [SortBy; 4] = [SortBy::FileOrder, SortBy::Name, SortBy::Size, SortBy::Bytes];
+
+    fn label(self) -> &'static str {
+        match self {
+            SortBy::FileOrder => "File order",
+            SortBy::Name => "Name",
+            SortBy::Size => "Size",
+            SortBy::Bytes => "Bytes",
+        }
+    }
+}
+
+/// Which images the list shows, and in what order.
+#[derive(Debug, Clone, Default)]
+pub struct ImageFilter {
+    /// Case-insensitive; a glob when it contains `*`, `?` or `[`, otherwise a
+    /// substring of the name.
+    pub query: String,
+    pub resolution: Option<(u16, u16)>,
+    pub format: Option<PixelFormat>,
+    /// `Some(true)` keeps only formats with an alpha channel.
+    pub alpha: Option<bool>,
+    pub sort: SortBy,
+    pub descending: bool,
+}
+
+impl ImageFilter {
+    fn is_glob(&self) -> bool {
+        self.query.contains(['*', '?', '['])
+    }
+
+    /// Indices of the images that pass, in display order. A glob that
+    /// doesn't parse matches nothing.
+    pub fn apply(&self, images: &[ImageResource]) -> Vec<usize> {
+        let query = self.query.trim().to_lowercase();
+        let pattern = if self.is_glob() { Some(Pattern::new(&query).ok()) } else { None };
+
+        let mut indices: Vec<usize> = images
+            .iter()
+            .enumerate()
+            .filter(|&(i, image)| {
+                let name = display_name(image, i).to_lowercase();
+                let name_matches = match &pattern {
+                    Some(Some(pattern)) => pattern.matches(&name),
+                    Some(None) => false,
+                    None => name.contains(&query),
+                };
+                name_matches
+                    && self.resolution.is_none_or(|size| size == (image.width, image.height))
+                    && self.format.is_none_or(|format| format == image.format)
+                    && self.alpha.is_none_or(|alpha| alpha == image.format.has_alpha())
+            })
+            .map(|(i, _)| i)
+            .collect();
+
+        // Ties stay in file order whichever way the list is sorted.
+        let compare = |&a: &usize, &b: &usize| -> Ordering {
+            let (x, y) = (&images[a], &images[b]);
+            let order = match self.sort {
+                SortBy::FileOrder => a.cmp(&b),
+                SortBy::Name => display_name(x, a).to_lowercase().cmp(&display_name(y, b).to_lowercase()),
+                SortBy::Size => (x.width as u32 * x.height as u32).cmp(&(y.width as u32 * y.height as u32)),
+                SortBy::Bytes => x.raw.len().cmp(&y.raw.len()),
+            };
+            let order = if self.descending { order.reverse() } else { order };
+            order.then(a.cmp(&b))
+        };
+        indices.sort_by(compare);
+        indices
+    }
+
+    /// Whether anything other than the sort order is set.
+    pub fn is_active(&self) -> bool {
+        !self.query.trim().is_empty()
+            || self.resolution.is_some()
+            || self.format.is_some()
+            || self.alpha.is_some()
+    }
+
+    /// The search box, filter combo boxes and sort controls. Resolutions are
+    /// offered from the images in the file.
+    pub fn ui(&mut self, ui: &mut egui::Ui, images: &[ImageResource]) {
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut self.query).hint_text("Filter names, e.g. *_sky*.tex"));
+            let clear = ui.add_enabled(self.is_active(), egui::Button::new("✖"));
+            if clear.on_hover_text("Clear filters").clicked() {
+                *self = Self { sort: self.sort, descending: self.descending, ..Self::default() };
+            }
+        });
+
+        let mut resolutions: Vec<(u16, u16)> = images.iter().map(|image| (image.width, image.height)).collect();
+        resolutions.sort_unstable();
+        resolutions.dedup();
+
+        egui::Grid::new("image_filter").num_columns(2).show(ui, |ui| {
+            ui.label("Size:");
+            egui::ComboBox::from_id_salt("filter_resolution")
+                .selected_text(self.resolution.map_or("Any".to_string(), |(w, h)| format!("{}x{}", w, h)))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.resolution, None, "Any");
+                    for (w, h) in resolutions {
+                        ui.selectable_value(&mut self.resolution, Some((w, h)), format!("{}x{}", w, h));
+                    }
+                });
+            ui.end_row();
+
+            ui.label("Format:");
+            egui::ComboBox::from_id_salt("filter_format")
+                .selected_text(self.format.map_or("Any".to_string(), |format| format.to_string()))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.format, None, "Any");
+                    for format in PixelFormat::ALL {
+                        ui.selectable_value(&mut self.format, Some(format), format.to_string());
+                    }
+                });
+            ui.end_row();
+
+            ui.label("Alpha:");
+            ui.horizontal(|ui| {
+                ui.selectable_value(&mut self.alpha, None, "Any");
+                ui.selectable_value(&mut self.alpha, Some(true), "Yes");
+                ui.selectable_value(&mut self.alpha, Some(false), "No");
+            });
+            ui.end_row();
+
+            ui.label("Sort:");
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_id_salt("filter_sort")
+                    .selected_text(self.sort.label())
+                    .show_ui(ui, |ui| {
+                        for sort in SortBy::ALL {
+                            ui.selectable_value(&mut self.sort, sort, sort.label());
+                        }
+                    });
+                let arrow = if self.descending { "⬇" } else { "⬆" };
+                if ui.button(arrow).on_hover_text("Reverse order").clicked() {
+                    self.descending = !self.descending;
+                }
+            });
+            ui.end_row();
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use resviewer::TextureHeader;
+
+    use super::*;
+
+    fn image(name: &str, width: u16, height: u16, format: PixelFormat) -> ImageResource {
+        let raw = vec![0; format.image_size(width, height)];
+        ImageResource {
+            name: Some(name.to_string()),
+            chunk_index: 0,
+            width,
+            height,
+            format,
+            format_guessed: false,
+            data: format.to_rgba8(&raw),
+            raw,
+            mipmaps: Vec::new(),
+            header: TextureHeader::default(),
+            data_offset: 0,
+            sub_images: Vec::new(),
+        }
+    }
+
+    /// 0: 4x4 RGB565, 1: 2x2 ARGB4444, 2: 4x4 BGRA8888, 3: 8x1 RGB565, 4: 2x2 ARGB1555.
+    fn images() -> Vec<ImageResource> {
+        vec![
+            image("Rock.tex", 4, 4, PixelFormat::Rgb565),
+            image("sky_01.tex", 2, 2, PixelFormat::Argb4444),
+            image("SKY_02.tex", 4, 4, PixelFormat::Bgra8888),
+            image("hud.tex", 8, 1, PixelFormat::Rgb565),
+            image("rocks_sky.tex", 2, 2, PixelFormat::Argb1555),
+        ]
+    }
+
+    fn query(query: &str) -> ImageFilter {
+        ImageFilter { query: query.to_string(), ..ImageFilter::default() }
+    }
+
+    fn sorted(sort: SortBy, descending: bool) -> Vec<usize> {
+        ImageFilter { sort, descending, ..ImageFilter::default() }.apply(&images())
+    }
+
+    #[test]
+    fn substring_ignores_case() {
+        assert_eq!(query("SKY").apply(&images()), [1, 2, 4]);
+        assert_eq!(query("  rock ").apply(&images()), [0, 4]);
+        assert_eq!(query("").apply(&images()), [0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn glob_matches_whole_names_ignoring_case() {
+        assert!(query("sky_*").is_glob());
+        assert!(!query("sky_").is_glob());
+        assert_eq!(query("sky_*").apply(&images()), [1, 2]);
+        assert_eq!(query("*_SKY*.tex").apply(&images()), [4]);
+        assert_eq!(query("?ud.tex").apply(&images()), [3]);
+    }
+
+    #[test]
+    fn invalid_glob_matches_nothing() {
+        assert!(query("sky[").apply(&images()).is_empty());
+    }
+
+    #[test]
+    fn resolution_format_and_alpha_filters() {
+        let filter = ImageFilter { resolution: Some((4, 4)), ..ImageFilter::default() };
+        assert_eq!(filter.apply(&images()), [0, 2]);
+        let filter = ImageFilter { format: Some(PixelFormat::Rgb565), ..ImageFilter::default() };
+        assert_eq!(filter.apply(&images()), [0, 3]);
+        let filter = ImageFilter { alpha: Some(true), ..ImageFilter::default() };
+        assert_eq!(filter.apply(&images()), [1, 2, 4]);
+        let filter = ImageFilter { alpha: Some(false), ..ImageFilter::default() };
+        assert_eq!(filter.apply(&images()), [0, 3]);
+        let filter = ImageFilter { resolution: Some((2, 2)), alpha: Some(true), ..query("rock") };
+        assert_eq!(filter.apply(&images()), [4]);
+    }
+
+    #[test]
+    fn sort_orders() {
+        assert_eq!(sorted(SortBy::FileOrder, false), [0, 1, 2, 3, 4]);
+        assert_eq!(sorted(SortBy::FileOrder, true), [4, 3, 2, 1, 0]);
+        assert_eq!(sorted(SortBy::Name, false), [3, 0, 4, 1, 2]);
+        assert_eq!(sorted(SortBy::Name, true), [2, 1, 4, 0, 3]);
+        // Sizes 16, 4, 16, 8, 4 and bytes 32, 8, 64, 16, 8.
+        assert_eq!(sorted(SortBy::Size, false), [1, 4, 3, 0, 2]);
+        assert_eq!(sorted(SortBy::Bytes, false), [1, 4, 3, 0, 2]);
+        assert_eq!(sorted(SortBy::Bytes, true), [2, 0, 3, 1, 4]);
+    }
+
+    #[test]
+    fn ties_stay_in_file_order() {
+        assert_eq!(sorted(SortBy::Size, true), [0, 2, 3, 1, 4]);
+    }
+
+    #[test]
+    fn clearing_keeps_nothing_but_the_sort() {
+        assert!(!ImageFilter { sort: SortBy::Name, descending: true, ..ImageFilter::default() }.is_active());
+        assert!(query("sky").is_active());
+        assert!(!query("  ").is_active());
+    }
+}
@@ -0,0 +1,89 @@
+//! Reading files on a worker thread so the window stays responsive.
+
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Arc;
+use std::thread;
+
+use eframe::egui;
+use resviewer::{IlffError, ImageResource, Progress, ResourceFile};
+
+pub type Loaded = Result<(ResourceFile, Vec<ImageResource>), IlffError>;
+
+enum Message {
+    Progress(Progress),
+    Done(Loaded, Vec<String>),
+}
+
+/// A file being read on a worker thread.
+pub struct Loader {
+    pub path: PathBuf,
+    pub progress: Progress,
+    cancel: Arc<AtomicBool>,
+    receiver: Receiver<Message>,
+}
+
+impl Loader {
+    /// Starts reading `path`; `ctx` is asked to repaint whenever there is news.
+    pub fn spawn(path: &Path, ctx: &egui::Context) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let worker_path = path.to_owned();
+        let worker_cancel = cancel.clone();
+        let ctx = ctx.clone();
+
+        thread::spawn(move || {
+            let mut debug_log = Vec::new();
+            let loaded = load(&worker_path, &mut debug_log, |progress| {
+                let _ = sender.send(Message::Progress(progress));
+                ctx.request_repaint();
+                if worker_cancel.load(Ordering::Relaxed) {
+                    ControlFlow::Break(())
+                } else {
+                    ControlFlow::Continue(())
+                }
+            });
+            let _ = sender.send(Message::Done(loaded, debug_log));
+            ctx.request_repaint();
+        });
+
+        Self {
+            path: path.to_owned(),
+            progress: Progress::default(),
+            cancel,
+            receiver,
+        }
+    }
+
+    /// Asks the worker to stop; it answers with [`IlffError::Cancelled`].
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+
+    /// Takes in the worker's progress and returns its result and debug log
+    /// once it has finished.
+    pub fn poll(&mut self) -> Option<(Loaded, Vec<String>)> {
+        loop {
+            match self.receiver.try_recv() {
+                Ok(Message::Progress(progress)) => self.progress = progress,
+                Ok(Message::Done(loaded, debug_log)) => return Some((loaded, debug_log)),
+                Err(TryRecvError::Empty) => return None,
+                Err(TryRecvError::Disconnected) => {
+                    let worker_died = std::io::Error::other("the loading thread stopped unexpectedly");
+                    return Some((Err(IlffError::Io { offset: 0, source: worker_died }), Vec::new()));
+                }
+            }
+        }
+    }
+}
+
+fn load<F>(path: &Path, debug_log: &mut Vec<String>, progress: F) -> Loaded
+where
+    F: FnMut(Progress) -> ControlFlow<()>,
+{
+    let file = ResourceFile::open_with_progress(path, debug_log, progress)?;
+    let images = file.images(debug_log)?;
+    Ok((file, images))
+}
@@ -0,0 +1,104 @@
+//! The resources of an archive that aren't textures: a list under the image
+//! list, and a viewer showing text as text and everything else as hex.
+
+use eframe::egui;
+use resviewer::resource::{self, decode_text, Resource, ResourceKind};
+use resviewer::{IlffFile, ImageResource};
+
+use super::structure;
+
+#[derive(Default)]
+pub struct ResourceViewer {
+    /// Every `BODY` of the open file that isn't one of its images.
+    resources: Vec<Resource>,
+    /// Index into `resources`.
+    selected: Option<usize>,
+    /// The selected resource decoded as text, when it is text.
+    text: Option<String>,
+    /// Show text as a hex dump anyway.
+    show_hex: bool,
+}
+
+impl ResourceViewer {
+    /// Lists the resources of a newly opened file other than `images`, see
+    /// [`resource::other_resources`]. `file` is `None` for a loose `.tex`.
+    pub fn set_file(&mut self, file: Option<&IlffFile>, images: &[ImageResource]) {
+        self.resources = file.map(|file| resource::other_resources(file, images)).unwrap_or_default();
+        self.deselect();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.resources.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> &Resource {
+        &self.resources[index]
+    }
+
+    pub fn selected(&self) -> Option<usize> {
+        self.selected
+    }
+
+    /// Selects resource `index` of `file`, decoding it as text if it is.
+    pub fn select(&mut self, index: usize, file: &IlffFile) {
+        let resource = &self.resources[index];
+        self.text = match resource.kind {
+            ResourceKind::Text | ResourceKind::Script => decode_text(resource.payload(file)),
+            _ => None,
+        };
+        self.selected = Some(index);
+    }
+
+    pub fn deselect(&mut self) {
+        self.selected = None;
+        self.text = None;
+    }
+
+    /// One row per resource with its name and kind.
+    pub fn list(&self, ui: &mut egui::Ui) -> Vec<(usize, egui::Response)> {
+        self.resources
+            .iter()
+            .enumerate()
+            .map(|(i, resource)| {
+                let label = format!("{} ({})", resource.display_name(), resource.kind);
+                (i, ui.selectable_label(self.selected == Some(i), label))
+            })
+            .collect()
+    }
+
+    /// The selected resource of `file`, if any.
+    pub fn show(&mut self, ui: &mut egui::Ui, file: &IlffFile) {
+        let Some(resource) = self.selected.map(|index| &self.resources[index]) else {
+            return;
+        };
+        let chunk = &file.chunks[resource.chunk_index];
+        ui.horizontal(|ui| {
+            ui.label(format!(
+                "{} | Kind: {} | Payload at {:#x} | Size: {} bytes",
+                resource.display_name(),
+                resource.kind,
+                chunk.payload_offset(),
+                chunk.payload.len()
+            ));
+            if self.text.is_some() {
+                ui.checkbox(&mut self.show_hex, "Show as hex");
+            }
+        });
+        if resource.kind == ResourceKind::Texture {
+            ui.label("This texture could not be decoded, see the debug console.");
+        }
+        ui.separator();
+        match &self.text {
+            Some(text) if !self.show_hex => {
+                egui::ScrollArea::both().auto_shrink([false, false]).show(ui, |ui| {
+                    ui.add(
+                        egui::TextEdit::multiline(&mut text.as_str())
+                            .code_editor()
+                            .desired_width(f32::INFINITY),
+                    );
+                });
+            }
+            _ => structure::hex_dump(ui, &chunk.payload, chunk.payload_offset(), false),
+        }
+    }
+}
@@ -0,0 +1,90 @@
+//! Settings kept between launches through eframe's storage.
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::thumbnails::SIZE_RANGE;
+use super::view::ViewSettings;
+use super::MyApp;
+
+/// How many files File → Recent remembers.
+const MAX_RECENT_FILES: usize = 10;
+
+/// Recently opened files and where file dialogs start.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RecentFiles {
+    /// Most recently opened first.
+    pub files: Vec<PathBuf>,
+    pub last_directory: Option<PathBuf>,
+}
+
+impl RecentFiles {
+    /// Moves `path` to the top of the list and remembers its directory.
+    pub fn add(&mut self, path: &Path) {
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_owned());
+        self.files.retain(|recent| *recent != path);
+        self.files.insert(0, path.clone());
+        self.files.truncate(MAX_RECENT_FILES);
+        self.last_directory = path.parent().map(Path::to_owned);
+    }
+
+    /// Remembers the directory of a file picked in a dialog, or the
+    /// directory itself when one was picked.
+    pub fn set_directory(&mut self, path: &Path) {
+        let dir = if path.is_dir() { Some(path) } else { path.parent() };
+        if let Some(dir) = dir.filter(|dir| !dir.as_os_str().is_empty()) {
+            self.last_directory = Some(dir.to_owned());
+        }
+    }
+
+    /// The directory file dialogs should start in.
+    pub fn dialog_directory(&self) -> &Path {
+        self.last_directory.as_deref().unwrap_or(Path::new("."))
+    }
+}
+
+/// Everything the app stores under [`eframe::APP_KEY`]. Window size and
+/// panel widths are kept by eframe itself.
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub recent: RecentFiles,
+    pub show_debug_console: bool,
+    pub show_structure: bool,
+    pub show_statistics: bool,
+    pub show_thumbnails: bool,
+    pub thumbnail_size: u32,
+    pub view: ViewSettings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self::from_app(&MyApp::default())
+    }
+}
+
+impl Settings {
+    pub fn from_app(app: &MyApp) -> Self {
+        Self {
+            recent: app.recent.clone(),
+            show_debug_console: app.show_debug_console,
+            show_structure: app.show_structure,
+            show_statistics: app.show_statistics,
+            show_thumbnails: app.show_thumbnails,
+            thumbnail_size: app.thumbnails.size,
+            view: app.view.settings(),
+        }
+    }
+
+    pub fn apply(self, app: &mut MyApp) {
+        app.recent = self.recent;
+        app.show_debug_console = self.show_debug_console;
+        app.show_structure = self.show_structure;
+        app.show_statistics = self.show_statistics;
+        app.show_thumbnails = self.show_thumbnails;
+        app.thumbnails.size = self.thumbnail_size.clamp(*SIZE_RANGE.start(), *SIZE_RANGE.end());
+        app.view.apply_settings(self.view);
+    }
+}
@@ -0,0 +1,60 @@
+//! The Statistics window: distinct values of every `BODY` sub-header field
+//! across the open file, next to the formats and mip counts they occur with.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use eframe::egui;
+use resviewer::texture::BODY_SUBHEADER_FIELDS;
+use resviewer::ImageResource;
+
+/// Images sharing one value of a field.
+#[derive(Default)]
+struct ValueStats {
+    count: usize,
+    formats: BTreeSet<String>,
+    level_counts: BTreeSet<usize>,
+}
+
+/// Per field, in the order of [`BODY_SUBHEADER_FIELDS`], every value seen.
+fn collect(images: &[ImageResource]) -> Vec<BTreeMap<u32, ValueStats>> {
+    let mut fields: Vec<BTreeMap<u32, ValueStats>> =
+        BODY_SUBHEADER_FIELDS.iter().map(|_| BTreeMap::new()).collect();
+    for image in images {
+        for (values, value) in fields.iter_mut().zip(image.header.values()) {
+            let stats = values.entry(value).or_default();
+            stats.count += 1;
+            stats.formats.insert(image.format.to_string());
+            stats.level_counts.insert(image.level_count());
+        }
+    }
+    fields
+}
+
+pub fn show(ui: &mut egui::Ui, images: &[ImageResource]) {
+    ui.label(format!("{} textures", images.len()));
+    egui::ScrollArea::vertical().show(ui, |ui| {
+        for ((name, _, _), values) in BODY_SUBHEADER_FIELDS.iter().zip(collect(images)) {
+            let title = match values.len() {
+                1 => format!("{}: always {}", name, values.keys().next().unwrap_or(&0)),
+                n => format!("{}: {} distinct values", name, n),
+            };
+            egui::CollapsingHeader::new(title).id_salt(name).show(ui, |ui| {
+                egui::Grid::new(("statistics", name)).num_columns(4).striped(true).show(ui, |ui| {
+                    ui.strong("Value");
+                    ui.strong("Textures");
+                    ui.strong("Formats");
+                    ui.strong("Mip levels");
+                    ui.end_row();
+                    for (value, stats) in &values {
+                        ui.monospace(format!("{} ({:#x})", value, value));
+                        ui.label(stats.count.to_string());
+                        ui.label(stats.formats.iter().cloned().collect::<Vec<_>>().join(", "));
+                        let levels: Vec<String> = stats.level_counts.iter().map(ToString::to_string).collect();
+                        ui.label(levels.join(", "));
+                        ui.end_row();
+                    }
+                });
+            });
+        }
+    });
+}
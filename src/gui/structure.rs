@@ -0,0 +1,187 @@
+//! The Structure window: every chunk of the open file with its header
+//! fields, and a hex dump of the selected chunk's payload.
+
+use eframe::egui;
+use egui::text::LayoutJob;
+use egui::{Color32, FontId, TextFormat};
+use resviewer::chunk::{fourcc, CHUNK_TYPE_BODY};
+use resviewer::texture::{BODY_SUBHEADER_FIELDS, BODY_SUBHEADER_SIZE};
+use resviewer::{IlffFile, IlffHeader, MAGIC_ILFF};
+
+/// Bytes per row of the hex dump.
+const ROW_BYTES: usize = 16;
+
+/// Colours cycled through the `BODY` sub-header fields, in the hex dump and
+/// in the field table alike.
+const FIELD_COLORS: [Color32; 4] = [
+    Color32::from_rgb(230, 150, 60),
+    Color32::from_rgb(90, 170, 230),
+    Color32::from_rgb(120, 200, 110),
+    Color32::from_rgb(210, 120, 200),
+];
+
+#[derive(Default)]
+pub struct StructureView {
+    /// Index into [`IlffFile::chunks`].
+    selected: Option<usize>,
+}
+
+impl StructureView {
+    /// Forgets the selection, e.g. after another file was opened.
+    pub fn reset(&mut self) {
+        self.selected = None;
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui, file: &IlffFile) {
+        egui::SidePanel::left("structure_tree")
+            .resizable(true)
+            .default_width(280.0)
+            .show_inside(ui, |ui| {
+                egui::ScrollArea::vertical().show(ui, |ui| self.tree(ui, file));
+            });
+        egui::CentralPanel::default().show_inside(ui, |ui| {
+            match self.selected.and_then(|index| file.chunks.get(index)) {
+                Some(chunk) => {
+                    ui.label(format!(
+                        "Chunk {} ({}), payload at {:#x}, {} bytes",
+                        self.selected.unwrap_or_default(),
+                        fourcc(chunk.tag),
+                        chunk.payload_offset(),
+                        chunk.payload.len()
+                    ));
+                    let annotate = chunk.tag == CHUNK_TYPE_BODY
+                        && chunk.payload.len() >= BODY_SUBHEADER_SIZE as usize;
+                    if annotate {
+                        body_fields(ui, &chunk.payload);
+                    }
+                    ui.separator();
+                    hex_dump(ui, &chunk.payload, chunk.payload_offset(), annotate);
+                }
+                None => {
+                    ui.label("Select a chunk to see its payload.");
+                }
+            }
+        });
+    }
+
+    fn tree(&mut self, ui: &mut egui::Ui, file: &IlffFile) {
+        egui::CollapsingHeader::new("File header").default_open(true).show(ui, |ui| {
+            fields(ui, "file_header", &[
+                ("magic", fourcc(MAGIC_ILFF)),
+                ("filesize", file.header.filesize.to_string()),
+                ("alignment", file.header.alignment.to_string()),
+                ("reserve", file.header.reserve.to_string()),
+                ("resource type", fourcc(file.resource_type)),
+                ("size", format!("{} bytes", IlffHeader::SIZE)),
+            ]);
+        });
+        for (i, chunk) in file.chunks.iter().enumerate() {
+            let id = ui.make_persistent_id(("structure_chunk", i));
+            egui::collapsing_header::CollapsingState::load_with_default_open(ui.ctx(), id, false)
+                .show_header(ui, |ui| {
+                    let label = format!("{:>4}  {}  @ {:#x}", i, fourcc(chunk.tag), chunk.offset);
+                    let label = egui::RichText::new(label).monospace();
+                    let response = ui.selectable_label(self.selected == Some(i), label);
+                    if response.clicked() {
+                        self.selected = Some(i);
+                    }
+                })
+                .body(|ui| {
+                    fields(ui, id, &[
+                        ("offset", format!("{:#x}", chunk.offset)),
+                        ("buffer_size", chunk.buffer_size.to_string()),
+                        ("alignment", chunk.alignment.to_string()),
+                        ("chunk_size", chunk.chunk_size.to_string()),
+                        ("padding", chunk.trailing.len().to_string()),
+                    ]);
+                });
+        }
+    }
+}
+
+/// A two-column grid of field names and values.
+fn fields(ui: &mut egui::Ui, id: impl std::hash::Hash, rows: &[(&str, String)]) {
+    egui::Grid::new(id).num_columns(2).striped(true).show(ui, |ui| {
+        for (name, value) in rows {
+            ui.label(*name);
+            ui.monospace(value);
+            ui.end_row();
+        }
+    });
+}
+
+/// The `BODY` sub-header fields with their position, raw bytes and value,
+/// coloured like their bytes in the hex dump.
+fn body_fields(ui: &mut egui::Ui, payload: &[u8]) {
+    egui::Grid::new("body_subheader").num_columns(4).striped(true).show(ui, |ui| {
+        ui.strong("Field");
+        ui.strong("Offset");
+        ui.strong("Bytes");
+        ui.strong("Value");
+        ui.end_row();
+        for (i, &(name, offset, size)) in BODY_SUBHEADER_FIELDS.iter().enumerate() {
+            let bytes = &payload[offset..offset + size];
+            let value = bytes.iter().rev().fold(0u32, |value, &b| value << 8 | b as u32);
+            let color = FIELD_COLORS[i % FIELD_COLORS.len()];
+            ui.label(egui::RichText::new(name).color(color));
+            ui.monospace(format!("+{}", offset));
+            ui.monospace(hex_bytes(bytes));
+            ui.monospace(format!("{} ({:#x})", value, value));
+            ui.end_row();
+        }
+    });
+}
+
+/// Offset, hex and ASCII columns for `payload`, which starts at
+/// `file_offset` in the file. Only visible rows are laid out, so large
+/// textures scroll smoothly. With `annotate` the sub-header bytes are
+/// coloured by field.
+pub(super) fn hex_dump(ui: &mut egui::Ui, payload: &[u8], file_offset: u64, annotate: bool) {
+    let font = FontId::monospace(12.0);
+    let row_height = ui.fonts(|fonts| fonts.row_height(&font));
+    let rows = payload.len().div_ceil(ROW_BYTES);
+    let text_color = ui.visuals().text_color();
+    egui::ScrollArea::both()
+        .auto_shrink([false, false])
+        .show_rows(ui, row_height, rows, |ui, range| {
+            for row in range {
+                let start = row * ROW_BYTES;
+                let bytes = &payload[start..(start + ROW_BYTES).min(payload.len())];
+                let mut job = LayoutJob::default();
+                let mut append = |text: &str, color: Color32| {
+                    job.append(text, 0.0, TextFormat::simple(font.clone(), color));
+                };
+                append(&format!("{:08x}  ", file_offset + start as u64), ui.visuals().weak_text_color());
+                for column in 0..ROW_BYTES {
+                    let text = match bytes.get(column) {
+                        Some(b) => format!("{:02x} ", b),
+                        None => "   ".to_string(),
+                    };
+                    let color = if annotate { field_color(start + column) } else { None };
+                    append(&text, color.unwrap_or(text_color));
+                    if column == ROW_BYTES / 2 - 1 {
+                        append(" ", text_color);
+                    }
+                }
+                let ascii: String = bytes
+                    .iter()
+                    .map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' })
+                    .collect();
+                append(&format!(" {}", ascii), text_color);
+                ui.label(job);
+            }
+        });
+}
+
+/// Colour of the sub-header field that byte `pos` of a `BODY` payload
+/// belongs to.
+fn field_color(pos: usize) -> Option<Color32> {
+    BODY_SUBHEADER_FIELDS
+        .iter()
+        .position(|&(_, offset, size)| (offset..offset + size).contains(&pos))
+        .map(|i| FIELD_COLORS[i % FIELD_COLORS.len()])
+}
+
+fn hex_bytes(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect::<Vec<_>>().join(" ")
+}
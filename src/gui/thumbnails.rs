@@ -0,0 +1,219 @@
+//! The thumbnail grid in the image list, with thumbnails scaled on a worker
+//! thread.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+use eframe::egui;
+use egui::{Color32, Sense, Vec2};
+use image::imageops;
+use image::RgbaImage;
+use resviewer::export::display_name;
+use resviewer::ImageResource;
+
+/// Smallest and largest thumbnail edge offered by the size slider, in points.
+pub const SIZE_RANGE: std::ops::RangeInclusive<u32> = 32..=256;
+
+/// A level-0 image to scale down.
+struct Job {
+    generation: u64,
+    index: usize,
+    size: u32,
+    pixels: RgbaImage,
+}
+
+/// A scaled thumbnail coming back from the worker.
+struct Done {
+    generation: u64,
+    index: usize,
+    image: egui::ColorImage,
+}
+
+struct Worker {
+    jobs: Sender<Job>,
+    done: Receiver<Done>,
+}
+
+impl Worker {
+    fn spawn(ctx: &egui::Context) -> Self {
+        let (jobs, job_receiver) = mpsc::channel::<Job>();
+        let (done_sender, done) = mpsc::channel();
+        let ctx = ctx.clone();
+        thread::spawn(move || {
+            for job in job_receiver {
+                let (width, height) = fit(job.pixels.width(), job.pixels.height(), job.size);
+                let scaled = imageops::thumbnail(&job.pixels, width, height);
+                let image = egui::ColorImage::from_rgba_unmultiplied(
+                    [scaled.width() as usize, scaled.height() as usize],
+                    scaled.as_raw(),
+                );
+                let done = Done { generation: job.generation, index: job.index, image };
+                if done_sender.send(done).is_err() {
+                    break;
+                }
+                ctx.request_repaint();
+            }
+        });
+        Self { jobs, done }
+    }
+}
+
+/// Thumbnails of the open file's images, requested as their cells scroll
+/// into view.
+pub struct Thumbnails {
+    /// Longest edge of a thumbnail, in points.
+    pub size: u32,
+    textures: HashMap<usize, egui::TextureHandle>,
+    /// Images sent to the worker and not back yet.
+    pending: HashSet<usize>,
+    /// Bumped whenever queued work goes stale, so late results are dropped.
+    generation: u64,
+    /// Started on first use.
+    worker: Option<Worker>,
+}
+
+impl Default for Thumbnails {
+    fn default() -> Self {
+        Self {
+            size: 96,
+            textures: HashMap::new(),
+            pending: HashSet::new(),
+            generation: 0,
+            worker: None,
+        }
+    }
+}
+
+impl Thumbnails {
+    /// Forgets every thumbnail, e.g. after another file was opened.
+    pub fn clear(&mut self) {
+        self.generation += 1;
+        self.textures.clear();
+        self.pending.clear();
+    }
+
+    /// Forgets the thumbnail of one image whose pixels changed.
+    pub fn invalidate(&mut self, index: usize) {
+        self.textures.remove(&index);
+        // A result already on its way still shows the old pixels.
+        if self.pending.remove(&index) {
+            self.generation += 1;
+            self.pending.clear();
+        }
+    }
+
+    fn receive(&mut self, ctx: &egui::Context) {
+        let Some(worker) = &self.worker else {
+            return;
+        };
+        while let Ok(done) = worker.done.try_recv() {
+            if done.generation != self.generation {
+                continue;
+            }
+            self.pending.remove(&done.index);
+            let texture = ctx.load_texture(
+                format!("thumbnail_{}", done.index),
+                done.image,
+                super::view::TEXTURE_OPTIONS,
+            );
+            self.textures.insert(done.index, texture);
+        }
+    }
+
+    fn request(&mut self, ctx: &egui::Context, index: usize, image: &ImageResource) {
+        if self.textures.contains_key(&index) || self.pending.contains(&index) {
+            return;
+        }
+        let (width, height, data) = image.level(0);
+        let Some(pixels) = RgbaImage::from_raw(width as u32, height as u32, data.to_vec()) else {
+            return;
+        };
+        let worker = self.worker.get_or_insert_with(|| Worker::spawn(ctx));
+        let job = Job { generation: self.generation, index, size: self.size, pixels };
+        if worker.jobs.send(job).is_ok() {
+            self.pending.insert(index);
+        }
+    }
+
+    /// Slider for the thumbnail size. Thumbnails are scaled again at the new
+    /// size once it is let go.
+    pub fn size_slider(&mut self, ui: &mut egui::Ui) {
+        let response = ui.add(egui::Slider::new(&mut self.size, SIZE_RANGE).text("Size"));
+        if response.drag_stopped() || (response.changed() && !response.dragged()) {
+            self.clear();
+        }
+    }
+
+    /// Draws a wrapping grid of thumbnails of `images[i]` for each `i` in
+    /// `shown`, with their names underneath, and returns the response of each
+    /// cell, so callers can handle clicks and context menus like they do for
+    /// the list.
+    pub fn grid(
+        &mut self,
+        ui: &mut egui::Ui,
+        images: &[ImageResource],
+        shown: &[usize],
+        selected: Option<usize>,
+    ) -> Vec<(usize, egui::Response)> {
+        let ctx = ui.ctx().clone();
+        self.receive(&ctx);
+
+        let edge = self.size as f32;
+        let label_height = ui.text_style_height(&egui::TextStyle::Small);
+        let cell = Vec2::new(edge, edge + label_height) + Vec2::splat(8.0);
+        let mut responses = Vec::new();
+        ui.horizontal_wrapped(|ui| {
+            ui.spacing_mut().item_spacing = Vec2::splat(4.0);
+            for &i in shown {
+                let image = &images[i];
+                let (rect, response) = ui.allocate_exact_size(cell, Sense::click());
+                if ui.is_rect_visible(rect) {
+                    let painter = ui.painter_at(rect);
+                    let visuals = ui.style().interact_selectable(&response, selected == Some(i));
+                    if selected == Some(i) || response.hovered() {
+                        painter.rect_filled(rect, 4.0, visuals.weak_bg_fill);
+                    }
+                    let image_rect = egui::Rect::from_min_size(rect.min + Vec2::splat(4.0), Vec2::splat(edge));
+                    match self.textures.get(&i) {
+                        Some(texture) => {
+                            let size = texture.size_vec2();
+                            let shown = size * (edge / size.x.max(size.y));
+                            let uv = egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0));
+                            painter.image(
+                                texture.id(),
+                                egui::Rect::from_center_size(image_rect.center(), shown),
+                                uv,
+                                Color32::WHITE,
+                            );
+                        }
+                        None => {
+                            self.request(&ctx, i, image);
+                            painter.rect_stroke(image_rect, 2.0, visuals.bg_stroke);
+                        }
+                    }
+                    painter.text(
+                        egui::pos2(rect.center().x, image_rect.bottom() + 2.0),
+                        egui::Align2::CENTER_TOP,
+                        display_name(image, i),
+                        egui::FontId::proportional(label_height),
+                        visuals.text_color(),
+                    );
+                }
+                responses.push((i, response.on_hover_text(display_name(image, i))));
+            }
+        });
+        responses
+    }
+}
+
+/// Scales `width` x `height` to fit in a `size` x `size` square, keeping the
+/// aspect ratio. Images are only made smaller, never larger.
+fn fit(width: u32, height: u32, size: u32) -> (u32, u32) {
+    let longest = width.max(height).max(1);
+    if longest <= size {
+        return (width.max(1), height.max(1));
+    }
+    let scale = |edge: u32| ((edge as u64 * size as u64 / longest as u64) as u32).max(1);
+    (scale(width), scale(height))
+}
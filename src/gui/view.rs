@@ -0,0 +1,306 @@
+//! The zoomable image view in the central panel.
+
+use std::borrow::Cow;
+
+use eframe::egui;
+use egui::{Color32, Pos2, Rect, Sense, Vec2};
+use resviewer::SubImage;
+use serde::{Deserialize, Serialize};
+
+/// Upload options for textures shown in the view: nearest-neighbour when
+/// zoomed in so pixels stay sharp, linear when zoomed out.
+pub const TEXTURE_OPTIONS: egui::TextureOptions = egui::TextureOptions {
+    magnification: egui::TextureFilter::Nearest,
+    minification: egui::TextureFilter::Linear,
+    wrap_mode: egui::TextureWrapMode::ClampToEdge,
+    mipmap_mode: None,
+};
+
+/// Size of one checkerboard square, in points.
+const CHECKER_SIZE: f32 = 8.0;
+
+/// What is painted behind transparent pixels.
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Background {
+    Checkerboard,
+    Color(Color32),
+}
+
+/// Which channels of the image are shown.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Channels {
+    pub red: bool,
+    pub green: bool,
+    pub blue: bool,
+    /// When off, every pixel is shown fully opaque.
+    pub alpha: bool,
+    /// Show only the alpha channel, as greyscale.
+    pub alpha_as_grey: bool,
+}
+
+impl Default for Channels {
+    fn default() -> Self {
+        Self { red: true, green: true, blue: true, alpha: true, alpha_as_grey: false }
+    }
+}
+
+impl Channels {
+    /// Masks RGBA8 pixels for upload; borrows them when every channel is on.
+    pub fn apply<'a>(&self, rgba: &'a [u8]) -> Cow<'a, [u8]> {
+        if *self == Channels::default() {
+            return Cow::Borrowed(rgba);
+        }
+        let mut out = rgba.to_vec();
+        for px in out.chunks_exact_mut(4) {
+            if self.alpha_as_grey {
+                px.copy_from_slice(&[px[3], px[3], px[3], 0xFF]);
+                continue;
+            }
+            let keep = [self.red, self.green, self.blue];
+            for (value, keep) in px.iter_mut().zip(keep) {
+                if !keep {
+                    *value = 0;
+                }
+            }
+            if !self.alpha {
+                px[3] = 0xFF;
+            }
+        }
+        Cow::Owned(out)
+    }
+}
+
+const ZOOM_PRESETS: [f32; 4] = [2.0, 4.0, 8.0, 16.0];
+const MIN_ZOOM: f32 = 1.0 / 32.0;
+const MAX_ZOOM: f32 = 64.0;
+
+/// Zoom and pan of the image view.
+///
+/// `zoom` counts screen pixels per texel, so 1:1 stays sharp on high-DPI
+/// displays.
+pub struct ImageView {
+    zoom: f32,
+    /// Offset of the image centre from the centre of the view, in points.
+    pan: Vec2,
+    /// Recompute `zoom` every frame so the image fills the view.
+    fit: bool,
+    pub background: Background,
+    pub channels: Channels,
+    /// Draw the sub-image rectangles of atlases over the image.
+    pub outlines: bool,
+    /// 2x2 texture repeated to draw the checkerboard.
+    checker: Option<egui::TextureHandle>,
+}
+
+impl Default for ImageView {
+    fn default() -> Self {
+        Self {
+            zoom: 1.0,
+            pan: Vec2::ZERO,
+            fit: true,
+            background: Background::Checkerboard,
+            channels: Channels::default(),
+            outlines: true,
+            checker: None,
+        }
+    }
+}
+
+/// The parts of [`ImageView`] kept between launches.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ViewSettings {
+    pub fit: bool,
+    pub zoom: f32,
+    pub background: Background,
+    pub outlines: bool,
+}
+
+impl Default for ViewSettings {
+    fn default() -> Self {
+        ImageView::default().settings()
+    }
+}
+
+impl ImageView {
+    pub fn settings(&self) -> ViewSettings {
+        ViewSettings { fit: self.fit, zoom: self.zoom, background: self.background, outlines: self.outlines }
+    }
+
+    pub fn apply_settings(&mut self, settings: ViewSettings) {
+        self.fit = settings.fit;
+        self.zoom = if settings.zoom.is_finite() && settings.zoom > 0.0 { settings.zoom } else { 1.0 };
+        self.background = settings.background;
+        self.outlines = settings.outlines;
+    }
+
+    /// Centres the image again, e.g. after another one was selected.
+    pub fn reset_pan(&mut self) {
+        self.pan = Vec2::ZERO;
+    }
+
+    /// Buttons for fit, 1:1 and the integer zoom presets.
+    pub fn toolbar(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Zoom:");
+            if ui.selectable_label(self.fit, "Fit").clicked() {
+                self.fit = true;
+                self.pan = Vec2::ZERO;
+            }
+            if ui.selectable_label(!self.fit && self.zoom == 1.0, "1:1").clicked() {
+                self.set_zoom(1.0);
+            }
+            for preset in ZOOM_PRESETS {
+                let label = format!("{}x", preset);
+                if ui.selectable_label(!self.fit && self.zoom == preset, label).clicked() {
+                    self.set_zoom(preset);
+                }
+            }
+            ui.label(format!("{:.0}%", self.zoom * 100.0));
+        });
+    }
+
+    /// Background and channel controls. Returns true when the channel
+    /// selection changed and textures have to be uploaded again.
+    pub fn display_toolbar(&mut self, ui: &mut egui::Ui) -> bool {
+        let before = self.channels;
+        ui.horizontal(|ui| {
+            ui.label("Background:");
+            if ui
+                .selectable_label(self.background == Background::Checkerboard, "Checkerboard")
+                .clicked()
+            {
+                self.background = Background::Checkerboard;
+            }
+            let mut color = match self.background {
+                Background::Color(color) => color,
+                Background::Checkerboard => ui.visuals().panel_fill,
+            };
+            if ui.color_edit_button_srgba(&mut color).changed() {
+                self.background = Background::Color(color);
+            }
+
+            ui.separator();
+            ui.label("Channels:");
+            let channels = &mut self.channels;
+            ui.add_enabled_ui(!channels.alpha_as_grey, |ui| {
+                ui.toggle_value(&mut channels.red, "R");
+                ui.toggle_value(&mut channels.green, "G");
+                ui.toggle_value(&mut channels.blue, "B");
+                ui.toggle_value(&mut channels.alpha, "A");
+            });
+            ui.toggle_value(&mut channels.alpha_as_grey, "Alpha as grey");
+        });
+        self.channels != before
+    }
+
+    fn set_zoom(&mut self, zoom: f32) {
+        self.fit = false;
+        self.zoom = zoom;
+        self.pan = Vec2::ZERO;
+    }
+
+    /// Paints `texture` into the rest of the panel and handles wheel zoom
+    /// around the cursor and drag to pan.
+    ///
+    /// Returns the response of the whole view area and the screen rectangle
+    /// the image was painted into.
+    pub fn show(&mut self, ui: &mut egui::Ui, texture: &egui::TextureHandle) -> (egui::Response, Rect) {
+        let view = ui.available_rect_before_wrap();
+        let response = ui.allocate_rect(view, Sense::click_and_drag());
+        let pixels_per_point = ui.ctx().pixels_per_point();
+        let texture_size = texture.size_vec2();
+
+        if self.fit {
+            let fit = view.size() * pixels_per_point / texture_size;
+            self.zoom = fit.x.min(fit.y).clamp(MIN_ZOOM, MAX_ZOOM);
+            self.pan = Vec2::ZERO;
+        }
+
+        if response.dragged() {
+            self.pan += response.drag_delta();
+            self.fit = false;
+        }
+
+        if let Some(cursor) = response.hover_pos() {
+            let scroll = ui.input(|i| i.smooth_scroll_delta.y);
+            let factor = ui.input(|i| i.zoom_delta()) * (scroll / 200.0).exp();
+            if factor != 1.0 {
+                let zoom = (self.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+                // Keep the texel under the cursor where it is.
+                let from_centre = cursor - view.center() - self.pan;
+                self.pan -= from_centre * (zoom / self.zoom - 1.0);
+                self.zoom = zoom;
+                self.fit = false;
+            }
+        }
+
+        let size = texture_size * self.zoom / pixels_per_point;
+        let rect = Rect::from_center_size(view.center() + self.pan, size);
+        let uv = Rect::from_min_max(Pos2::ZERO, Pos2::new(1.0, 1.0));
+        let painter = ui.painter_at(view);
+        match self.background {
+            Background::Color(color) => {
+                painter.rect_filled(rect, 0.0, color);
+            }
+            Background::Checkerboard => {
+                let checker = self.checker.get_or_insert_with(|| load_checker(ui.ctx()));
+                let repeats = rect.size() / (2.0 * CHECKER_SIZE);
+                let checker_uv = Rect::from_min_max(Pos2::ZERO, repeats.to_pos2());
+                painter.image(checker.id(), rect, checker_uv, Color32::WHITE);
+            }
+        }
+        painter.image(texture.id(), rect, uv, Color32::WHITE);
+        (response, rect)
+    }
+}
+
+/// The texel of a `width` x `height` image under `pos`, when the image was
+/// painted into `rect`.
+pub fn texel_at(pos: Pos2, rect: Rect, width: u16, height: u16) -> Option<(u16, u16)> {
+    if !rect.contains(pos) {
+        return None;
+    }
+    let uv = (pos - rect.min) / rect.size();
+    let x = (uv.x * width as f32) as u16;
+    let y = (uv.y * height as f32) as u16;
+    Some((x.min(width.saturating_sub(1)), y.min(height.saturating_sub(1))))
+}
+
+/// Outlines `frames` of a `width` x `height` image that was painted into
+/// `rect`, clipped to `clip`; the `selected` one is filled as well.
+pub fn paint_frames(
+    ui: &egui::Ui,
+    clip: Rect,
+    rect: Rect,
+    (width, height): (u16, u16),
+    frames: &[SubImage],
+    selected: Option<usize>,
+) {
+    let painter = ui.painter().with_clip_rect(clip);
+    let scale = rect.size() / Vec2::new(width.max(1) as f32, height.max(1) as f32);
+    for (i, frame) in frames.iter().enumerate() {
+        let min = rect.min + Vec2::new(frame.x as f32, frame.y as f32) * scale;
+        let frame_rect = Rect::from_min_size(min, Vec2::new(frame.width as f32, frame.height as f32) * scale);
+        if selected == Some(i) {
+            painter.rect_filled(frame_rect, 0.0, Color32::from_rgba_unmultiplied(255, 220, 0, 48));
+            painter.rect_stroke(frame_rect, 0.0, egui::Stroke::new(2.0, Color32::from_rgb(255, 220, 0)));
+        } else {
+            painter.rect_stroke(frame_rect, 0.0, egui::Stroke::new(1.0, Color32::from_rgb(0, 200, 255)));
+        }
+    }
+}
+
+fn load_checker(ctx: &egui::Context) -> egui::TextureHandle {
+    let light = Color32::from_gray(0xCC);
+    let dark = Color32::from_gray(0x99);
+    let image = egui::ColorImage {
+        size: [2, 2],
+        pixels: vec![light, dark, dark, light],
+    };
+    let options = egui::TextureOptions {
+        wrap_mode: egui::TextureWrapMode::Repeat,
+        ..egui::TextureOptions::NEAREST
+    };
+    ctx.load_texture("checkerboard", image, options)
+}
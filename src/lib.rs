@@ -0,0 +1,40 @@
+//! Reading of the ILFF resource containers (`.res`) used by *Project I.G.I* and
+//! *I.G.I 2: Covert Strike*.
+//!
+//! An ILFF file starts with a small header followed by a flat list of chunks.
+//! Texture archives (`IRES`) store their images as `NAME`/`BODY` chunk pairs;
+//! other pairs hold text, meshes, sounds or scripts, see [`resource`].
+//!
+//! [`IlffFile`] keeps every chunk exactly as read; the texture view in
+//! [`texture`] is built on top of it. Loose `.tex` files start with a `LOOP`
+//! header instead and are read by [`TexFile`]; [`ResourceFile`] opens either,
+//! telling them apart by their magic number.
+//!
+//! ```no_run
+//! let mut log = Vec::new();
+//! let images = resviewer::read_ilff_file("textures.res", &mut log)?;
+//! for image in &images {
+//!     println!("{:?}: {}x{}", image.name, image.width, image.height);
+//! }
+//! # Ok::<(), resviewer::IlffError>(())
+//! ```
+
+pub mod chunk;
+pub mod container;
+pub mod error;
+pub mod export;
+pub mod format;
+pub mod pack;
+pub mod resource;
+pub mod tex;
+pub mod texture;
+
+pub use chunk::Chunk;
+pub use container::{
+    read_ilff, read_ilff_file, IlffFile, IlffHeader, Progress, ResourceFile, MAGIC_ILFF, RES_TYPE_IRES,
+};
+pub use error::IlffError;
+pub use format::PixelFormat;
+pub use resource::{Resource, ResourceKind};
+pub use tex::{TexFile, MAGIC_LOOP};
+pub use texture::{ImageResource, MipLevel, SubImage, TextureHeader};
@@ -0,0 +1,323 @@
+//! Building new `IRES` archives from images.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use image::RgbaImage;
+use serde::Deserialize;
+
+use crate::chunk::{Chunk, CHUNK_TYPE_BODY, CHUNK_TYPE_NAME};
+use crate::container::{IlffFile, IlffHeader, RES_TYPE_IRES};
+use crate::format::PixelFormat;
+use crate::texture::{self, full_mip_count};
+
+/// Alignment used for the file header and every chunk of packed archives.
+pub const PACK_ALIGNMENT: u32 = 4;
+
+/// One texture to pack.
+#[derive(Debug, Clone)]
+pub struct PackEntry {
+    /// Stored in the `NAME` chunk, e.g. `rock.tex`.
+    pub name: String,
+    pub pixels: RgbaImage,
+    pub format: PixelFormat,
+    /// Whether to generate mip levels down to 1x1.
+    pub mipmaps: bool,
+}
+
+/// Controls how [`pack_dir`] packs a directory, read from TOML or JSON.
+///
+/// ```toml
+/// format = "argb1555"
+///
+/// [[images]]
+/// file = "rock.png"
+/// name = "rock.tex"
+/// format = "argb4444"
+/// mipmaps = true
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    /// Format for images that don't set their own.
+    #[serde(default)]
+    pub format: Option<PixelFormat>,
+    #[serde(default)]
+    pub mipmaps: bool,
+    /// Images in archive order; when empty, every PNG in the directory is
+    /// packed in file name order.
+    #[serde(default)]
+    pub images: Vec<ManifestImage>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ManifestImage {
+    /// PNG file, relative to the packed directory.
+    pub file: PathBuf,
+    /// Defaults to the file name with `.tex` instead of `.png`.
+    pub name: Option<String>,
+    #[serde(default)]
+    pub format: Option<PixelFormat>,
+    pub mipmaps: Option<bool>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum PackError {
+    #[error("{}: {source}", path.display())]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("{}: {source}", path.display())]
+    Image {
+        path: PathBuf,
+        #[source]
+        source: image::ImageError,
+    },
+    #[error("{}: invalid manifest: {message}", path.display())]
+    Manifest { path: PathBuf, message: String },
+    #[error("{name}: {width}x{height} is larger than the 65535x65535 a texture can hold")]
+    TooLarge { name: String, width: u32, height: u32 },
+    #[error("{name}: {format} textures can't be stored, IGI keeps 32-bit textures as BGRA8888")]
+    UnsupportedFormat { name: String, format: PixelFormat },
+}
+
+impl Manifest {
+    /// Reads a manifest, as JSON if the file ends in `.json` and as TOML
+    /// otherwise.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, PackError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).map_err(|source| PackError::Io {
+            path: path.to_owned(),
+            source,
+        })?;
+        let is_json = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+        let parsed = if is_json {
+            serde_json::from_str(&text).map_err(|e| e.to_string())
+        } else {
+            toml::from_str(&text).map_err(|e| e.to_string())
+        };
+        parsed.map_err(|message| PackError::Manifest { path: path.to_owned(), message })
+    }
+}
+
+/// Builds an `IRES` archive with a `NAME` and a `BODY` chunk per entry.
+///
+/// Sizes, offsets and padding are filled in by [`IlffFile::update_layout`].
+pub fn pack(entries: &[PackEntry]) -> Result<IlffFile, PackError> {
+    let mut chunks = Vec::with_capacity(entries.len() * 2);
+    for entry in entries {
+        let (width, height) = entry.pixels.dimensions();
+        let (Ok(width_16), Ok(height_16)) = (u16::try_from(width), u16::try_from(height)) else {
+            return Err(PackError::TooLarge { name: entry.name.clone(), width, height });
+        };
+        let mip_count = if entry.mipmaps {
+            full_mip_count(width_16, height_16)
+        } else {
+            0
+        };
+        let body = texture::new_body(&entry.pixels, entry.format, mip_count).ok_or_else(|| {
+            PackError::UnsupportedFormat { name: entry.name.clone(), format: entry.format }
+        })?;
+        let mut name = entry.name.clone().into_bytes();
+        name.push(0);
+        chunks.push(new_chunk(CHUNK_TYPE_NAME, name));
+        chunks.push(new_chunk(CHUNK_TYPE_BODY, body));
+    }
+
+    let mut file = IlffFile {
+        header: IlffHeader { filesize: 0, alignment: PACK_ALIGNMENT, reserve: 0 },
+        resource_type: RES_TYPE_IRES,
+        chunks,
+    };
+    file.update_layout();
+    Ok(file)
+}
+
+/// Packs the PNGs in `dir` as listed by `manifest`, using `format` for images
+/// that neither they nor the manifest give a format for.
+pub fn pack_dir<P: AsRef<Path>>(
+    dir: P,
+    manifest: &Manifest,
+    format: PixelFormat,
+) -> Result<IlffFile, PackError> {
+    let dir = dir.as_ref();
+    let images = if manifest.images.is_empty() {
+        png_files(dir)?
+            .into_iter()
+            .map(|file| ManifestImage { file, name: None, format: None, mipmaps: None })
+            .collect()
+    } else {
+        manifest.images.clone()
+    };
+
+    let mut entries = Vec::with_capacity(images.len());
+    for image in images {
+        let path = dir.join(&image.file);
+        let pixels = image::open(&path)
+            .map_err(|source| PackError::Image { path, source })?
+            .to_rgba8();
+        let name = image.name.unwrap_or_else(|| tex_name(&image.file));
+        entries.push(PackEntry {
+            name,
+            pixels,
+            format: image.format.or(manifest.format).unwrap_or(format),
+            mipmaps: image.mipmaps.unwrap_or(manifest.mipmaps),
+        });
+    }
+    pack(&entries)
+}
+
+fn new_chunk(tag: u32, payload: Vec<u8>) -> Chunk {
+    Chunk {
+        tag,
+        alignment: PACK_ALIGNMENT,
+        buffer_size: payload.len() as u32,
+        chunk_size: 0,
+        offset: 0,
+        payload,
+        trailing: Vec::new(),
+    }
+}
+
+/// PNG files directly inside `dir`, sorted by name.
+fn png_files(dir: &Path) -> Result<Vec<PathBuf>, PackError> {
+    let io_error = |source| PackError::Io { path: dir.to_owned(), source };
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir).map_err(io_error)? {
+        let path = entry.map_err(io_error)?.path();
+        let is_png = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("png"));
+        if is_png && path.is_file() {
+            files.push(PathBuf::from(path.file_name().unwrap()));
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// `rock.png` becomes `rock.tex`.
+fn tex_name(file: &Path) -> String {
+    let stem = file.file_stem().unwrap_or(file.as_os_str());
+    format!("{}.tex", stem.to_string_lossy())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::chunk::padding;
+    use crate::container::read_ilff;
+
+    fn entry(name: &str, width: u32, height: u32, format: PixelFormat, mipmaps: bool) -> PackEntry {
+        let pixels = RgbaImage::from_pixel(width, height, image::Rgba([255, 0, 0, 255]));
+        PackEntry { name: name.to_string(), pixels, format, mipmaps }
+    }
+
+    /// Writes `text` to a file named `name` in the temp dir and loads it.
+    fn load_manifest(name: &str, text: &str) -> Result<Manifest, PackError> {
+        let path = std::env::temp_dir().join(format!("resviewer_{}_{}", std::process::id(), name));
+        fs::write(&path, text).unwrap();
+        let manifest = Manifest::load(&path);
+        fs::remove_file(path).unwrap();
+        manifest
+    }
+
+    #[test]
+    fn packed_archive_reads_back() {
+        let entries = [
+            entry("rock.tex", 8, 4, PixelFormat::Argb1555, true),
+            entry("sky_01.tex", 3, 5, PixelFormat::Rgb565, false),
+            entry("hud.tex", 2, 2, PixelFormat::Bgra8888, true),
+        ];
+        let mut bytes = Vec::new();
+        pack(&entries).unwrap().write(&mut bytes).unwrap();
+
+        let file = IlffFile::read(&mut Cursor::new(&bytes), &mut Vec::new()).unwrap();
+        assert_eq!(file.header.filesize as usize, bytes.len());
+        for chunk in &file.chunks {
+            assert_eq!(chunk.offset % PACK_ALIGNMENT as u64, 0);
+            assert_eq!(chunk.trailing, vec![0; padding(chunk.end(), PACK_ALIGNMENT) as usize]);
+        }
+
+        let images = read_ilff(&mut Cursor::new(&bytes), &mut Vec::new()).unwrap();
+        let read: Vec<_> = images
+            .iter()
+            .map(|image| {
+                let name = image.name.as_deref().unwrap();
+                (name, image.width, image.height, image.format, image.level_count())
+            })
+            .collect();
+        assert_eq!(
+            read,
+            [
+                ("rock.tex", 8, 4, PixelFormat::Argb1555, 4),
+                ("sky_01.tex", 3, 5, PixelFormat::Rgb565, 1),
+                ("hud.tex", 2, 2, PixelFormat::Bgra8888, 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn images_above_65535_pixels_are_too_large() {
+        let err = pack(&[entry("wide.tex", 65536, 1, PixelFormat::Rgb565, false)]).unwrap_err();
+        assert!(matches!(err, PackError::TooLarge { width: 65536, height: 1, .. }), "{err:?}");
+    }
+
+    #[test]
+    fn toml_manifest_overrides_defaults_per_image() {
+        let manifest = load_manifest(
+            "manifest.toml",
+            r#"
+            format = "argb1555"
+            mipmaps = true
+
+            [[images]]
+            file = "rock.png"
+
+            [[images]]
+            file = "hud.png"
+            name = "hud_main.tex"
+            format = "argb4444"
+            mipmaps = false
+            "#,
+        )
+        .unwrap();
+        assert_eq!(manifest.format, Some(PixelFormat::Argb1555));
+        assert!(manifest.mipmaps);
+        let [rock, hud] = &manifest.images[..] else {
+            panic!("{:?}", manifest.images);
+        };
+        assert_eq!((rock.name.as_deref(), rock.format, rock.mipmaps), (None, None, None));
+        assert_eq!(
+            (hud.name.as_deref(), hud.format, hud.mipmaps),
+            (Some("hud_main.tex"), Some(PixelFormat::Argb4444), Some(false))
+        );
+    }
+
+    #[test]
+    fn json_manifest() {
+        let manifest = load_manifest(
+            "manifest.json",
+            r#"{"format": "rgb565", "images": [{"file": "a.png", "format": "bgra8888", "mipmaps": true}]}"#,
+        )
+        .unwrap();
+        assert_eq!(manifest.format, Some(PixelFormat::Rgb565));
+        assert!(!manifest.mipmaps);
+        assert_eq!(manifest.images[0].file, Path::new("a.png"));
+        assert_eq!(manifest.images[0].format, Some(PixelFormat::Bgra8888));
+        assert_eq!(manifest.images[0].mipmaps, Some(true));
+    }
+
+    #[test]
+    fn manifest_rejects_unknown_fields() {
+        let err = load_manifest("unknown.toml", "fromat = \"rgb565\"\n").unwrap_err();
+        assert!(matches!(err, PackError::Manifest { .. }), "{err:?}");
+        let err = load_manifest("unknown.json", r#"{"images": [{"file": "a.png", "mip": true}]}"#).unwrap_err();
+        assert!(matches!(err, PackError::Manifest { .. }), "{err:?}");
+    }
+}
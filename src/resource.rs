@@ -0,0 +1,237 @@
+//! What each `NAME`/`BODY` pair of an `IRES` archive holds.
+//!
+//! Besides textures, archives bundle localised strings, meshes, sounds and
+//! scripts. The kind is taken from the extension in the `NAME` chunk when it
+//! is a known one, and sniffed from the `BODY` payload otherwise.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::path::Path;
+
+use byteorder::{ByteOrder, LittleEndian};
+
+use crate::chunk::{CHUNK_TYPE_BODY, CHUNK_TYPE_NAME};
+use crate::container::IlffFile;
+use crate::format::PixelFormat;
+use crate::texture::{ImageResource, BODY_SUBHEADER_SIZE};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ResourceKind {
+    Texture,
+    Text,
+    Script,
+    Mesh,
+    Sound,
+    /// Anything not recognised.
+    Binary,
+}
+
+impl fmt::Display for ResourceKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ResourceKind::Texture => "texture",
+            ResourceKind::Text => "text",
+            ResourceKind::Script => "script",
+            ResourceKind::Mesh => "mesh",
+            ResourceKind::Sound => "sound",
+            ResourceKind::Binary => "binary",
+        };
+        f.write_str(name)
+    }
+}
+
+impl ResourceKind {
+    /// The kind a file name's extension stands for, if it is a known one.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let extension = Path::new(name).extension()?.to_str()?.to_ascii_lowercase();
+        let kind = match extension.as_str() {
+            "tex" | "spr" | "pic" => ResourceKind::Texture,
+            "txt" | "str" | "lng" | "ini" | "cfg" | "csv" => ResourceKind::Text,
+            "qsc" | "qvm" => ResourceKind::Script,
+            "mef" | "msh" | "mesh" => ResourceKind::Mesh,
+            "wav" | "snd" => ResourceKind::Sound,
+            _ => return None,
+        };
+        Some(kind)
+    }
+
+    /// Guesses the kind from the payload alone: a `BODY` sub-header that
+    /// matches the payload size, a RIFF header, or mostly printable text.
+    pub fn sniff(payload: &[u8]) -> Self {
+        if looks_like_texture(payload) {
+            ResourceKind::Texture
+        } else if payload.starts_with(b"RIFF") {
+            ResourceKind::Sound
+        } else if decode_text(payload).is_some() {
+            ResourceKind::Text
+        } else {
+            ResourceKind::Binary
+        }
+    }
+
+    /// [`ResourceKind::from_name`], falling back to [`ResourceKind::sniff`].
+    pub fn detect(name: Option<&str>, payload: &[u8]) -> Self {
+        name.and_then(Self::from_name).unwrap_or_else(|| Self::sniff(payload))
+    }
+}
+
+/// One `BODY` chunk with the name of the `NAME` before it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Resource {
+    pub name: Option<String>,
+    /// Index of the `BODY` chunk in [`IlffFile::chunks`].
+    pub chunk_index: usize,
+    pub kind: ResourceKind,
+}
+
+impl Resource {
+    /// Its `NAME`, or `Chunk {chunk_index}` when it has none.
+    pub fn display_name(&self) -> String {
+        self.name.clone().unwrap_or_else(|| format!("Chunk {}", self.chunk_index))
+    }
+
+    /// The `BODY` payload in `file`, the archive this was listed from.
+    pub fn payload<'a>(&self, file: &'a IlffFile) -> &'a [u8] {
+        &file.chunks[self.chunk_index].payload
+    }
+}
+
+/// Every `BODY` chunk of `file` with its name and kind, whatever the
+/// resource type of the file.
+pub fn resources(file: &IlffFile) -> Vec<Resource> {
+    let mut resources = Vec::new();
+    let mut current_name: Option<String> = None;
+    for (chunk_index, chunk) in file.chunks.iter().enumerate() {
+        match chunk.tag {
+            CHUNK_TYPE_NAME => current_name = Some(chunk_name(&chunk.payload)),
+            CHUNK_TYPE_BODY => {
+                let kind = ResourceKind::detect(current_name.as_deref(), &chunk.payload);
+                resources.push(Resource { name: current_name.clone(), chunk_index, kind });
+            }
+            _ => {}
+        }
+    }
+    resources
+}
+
+/// The [`resources`] of `file` that none of `images` was decoded from:
+/// everything but the textures, plus textures that couldn't be decoded and
+/// those of archives that aren't texture archives.
+pub fn other_resources(file: &IlffFile, images: &[ImageResource]) -> Vec<Resource> {
+    let decoded: HashSet<usize> = images.iter().map(|image| image.chunk_index).collect();
+    resources(file)
+        .into_iter()
+        .filter(|resource| !decoded.contains(&resource.chunk_index))
+        .collect()
+}
+
+/// The name stored in a `NAME` payload, without its terminating NULs.
+pub(crate) fn chunk_name(payload: &[u8]) -> String {
+    String::from_utf8_lossy(payload).trim_end_matches('\0').to_string()
+}
+
+/// Whether `payload` starts with a `BODY` sub-header whose full-size image
+/// fits in the rest of the payload.
+///
+/// A `body_type` that isn't a known pixel format still counts when the rest
+/// holds 16 to 32 bits per pixel, mip levels included, so that the texture
+/// is reported as one of an unknown type rather than taken for binary data.
+fn looks_like_texture(payload: &[u8]) -> bool {
+    if payload.len() < BODY_SUBHEADER_SIZE as usize {
+        return false;
+    }
+    let body_type = LittleEndian::read_u32(payload);
+    let width = LittleEndian::read_u16(&payload[22..]);
+    let height = LittleEndian::read_u16(&payload[24..]);
+    if width == 0 || height == 0 {
+        return false;
+    }
+    let pixels = payload.len() - BODY_SUBHEADER_SIZE as usize;
+    match PixelFormat::from_body_type(body_type) {
+        Some(format) => format.image_size(width, height) <= pixels,
+        None => {
+            let area = width as usize * height as usize;
+            (area * 2..=area * 6).contains(&pixels)
+        }
+    }
+}
+
+/// Decodes `payload` as text when at least 95% of the characters other than
+/// NUL are printable and NULs make up at most half of it: UTF-16LE when it
+/// has a byte order mark or looks like it, otherwise UTF-8, or Latin-1 when
+/// it isn't valid UTF-8. NULs between strings become line breaks; trailing
+/// ones are dropped.
+pub fn decode_text(payload: &[u8]) -> Option<String> {
+    let end = payload.iter().rposition(|&b| b != 0).map_or(0, |last| last + 1);
+    // Keep a NUL that completes the last UTF-16 unit.
+    let end = if !end.is_multiple_of(2) && end < payload.len() { end + 1 } else { end };
+    let bytes = &payload[..end];
+    if bytes.is_empty() {
+        return None;
+    }
+
+    let text = if is_utf16le(bytes) {
+        let units: Vec<u16> = bytes.chunks_exact(2).map(LittleEndian::read_u16).collect();
+        String::from_utf16(&units).ok()?
+    } else {
+        match std::str::from_utf8(bytes) {
+            Ok(text) => text.to_string(),
+            Err(_) => bytes.iter().map(|&b| b as char).collect(),
+        }
+    };
+    let text = text.trim_start_matches('\u{feff}');
+
+    // NULs separate strings, but zero-filled data is no text.
+    let nuls = text.chars().filter(|&c| c == '\0').count();
+    let total = text.chars().count() - nuls;
+    let printable = text
+        .chars()
+        .filter(|&c| c != '\0' && (!c.is_control() || matches!(c, '\n' | '\r' | '\t')))
+        .count();
+    let is_text = total > 0 && nuls <= total && printable * 100 >= total * 95;
+    is_text.then(|| text.trim_end_matches('\0').replace('\0', "\n"))
+}
+
+/// A byte order mark, or ASCII-range text with every other byte 0.
+fn is_utf16le(bytes: &[u8]) -> bool {
+    if !bytes.len().is_multiple_of(2) {
+        return false;
+    }
+    if bytes.starts_with(&[0xFF, 0xFE]) {
+        return true;
+    }
+    let units = bytes.len() / 2;
+    let high_zero = bytes.chunks_exact(2).filter(|unit| unit[1] == 0 && unit[0] != 0).count();
+    units >= 2 && high_zero * 10 >= units * 9
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nul_separated_strings_are_text() {
+        assert_eq!(decode_text(b"one\0two\0three\0\0\0").as_deref(), Some("one\ntwo\nthree"));
+        let utf16: Vec<u8> = "Mission 1\0Goal\0".encode_utf16().flat_map(u16::to_le_bytes).collect();
+        assert_eq!(decode_text(&utf16).as_deref(), Some("Mission 1\nGoal"));
+    }
+
+    #[test]
+    fn zero_filled_data_is_not_text() {
+        let mut payload = vec![0u8; 400];
+        payload[200] = b'x';
+        assert_eq!(decode_text(&payload), None);
+        assert_eq!(ResourceKind::sniff(&payload), ResourceKind::Binary);
+        assert_eq!(ResourceKind::sniff(&[0; 64]), ResourceKind::Binary);
+    }
+
+    #[test]
+    fn unknown_body_type_is_still_a_texture() {
+        // A 4x4 sub-header with body_type 99 and 16 bits per pixel.
+        let mut payload = vec![0u8; BODY_SUBHEADER_SIZE as usize + 32];
+        payload[0] = 99;
+        payload[22] = 4;
+        payload[24] = 4;
+        assert_eq!(ResourceKind::sniff(&payload), ResourceKind::Texture);
+    }
+}
@@ -0,0 +1,351 @@
+//! Loose `.tex` files, which start with a `LOOP` header instead of being
+//! wrapped in an ILFF container.
+//!
+//! The layout this reader expects, little endian:
+//!
+//! | offset | size | field                                                |
+//! |--------|------|------------------------------------------------------|
+//! | 0      | 4    | magic, `LOOP`                                        |
+//! | 4      | 4    | version                                              |
+//! | 8      | 4    | mode, the same values as `body_type` of a `BODY`     |
+//! | 12     | 4    | number of mip levels after the full-size image       |
+//! | 16     | 4    | number of sub-images, 0 for a plain texture          |
+//! | 20     | 2    | width                                                |
+//! | 22     | 2    | height                                               |
+//! | 24     | 8    | unknown                                              |
+//!
+//! The header is followed by a mip table of one width/height pair (2 x u16)
+//! per mip level, then a sub-image table with the x, y, width and height
+//! (4 x u16) of each frame of an atlas, then the pixels of the full-size
+//! image and of each mip level in turn.
+
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::ops::ControlFlow;
+use std::path::Path;
+
+use byteorder::{LittleEndian, ReadBytesExt};
+
+use crate::container::{check_header_size, Progress};
+use crate::error::{IlffError, Result};
+use crate::format::PixelFormat;
+use crate::texture::{self, ImageResource, SubImage, TextureHeader};
+
+/// Magic number of a loose `.tex` file.
+pub const MAGIC_LOOP: u32 = 0x504F4F4C; // 'LOOP'
+
+/// The fixed part of a `LOOP` header.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LoopHeader {
+    pub version: u32,
+    /// Pixel format, see [`PixelFormat::from_body_type`].
+    pub mode: u32,
+    pub mip_count: u32,
+    pub image_count: u32,
+    pub width: u16,
+    pub height: u16,
+    pub unk1: u32,
+    pub unk2: u32,
+}
+
+impl LoopHeader {
+    /// Size of the fixed header on disk, including the magic.
+    pub const SIZE: u32 = 32;
+
+    /// Size of the mip and sub-image tables after the fixed header.
+    fn tables_size(&self) -> u64 {
+        self.mip_count as u64 * 4 + self.image_count as u64 * 8
+    }
+}
+
+/// A loose `.tex` file as read, with its pixels still encoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TexFile {
+    /// File name, used as the texture's name.
+    pub name: Option<String>,
+    pub header: LoopHeader,
+    /// Width and height of each mip level from the mip table, largest first.
+    pub mip_sizes: Vec<(u16, u16)>,
+    /// The sub-image table; rectangles are kept as read, even when they
+    /// don't fit the image.
+    pub sub_images: Vec<SubImage>,
+    /// Everything after the tables: the full-size image and its mip levels.
+    pub pixels: Vec<u8>,
+}
+
+impl TexFile {
+    /// Opens `filename` and reads it, see [`TexFile::read`].
+    pub fn open<P: AsRef<Path>>(filename: P, debug_log: &mut Vec<String>) -> Result<Self> {
+        let filename = filename.as_ref();
+        debug_log.push(format!("Opening file: {}", filename.display()));
+        let file = File::open(filename).map_err(IlffError::open(filename))?;
+        let mut tex = Self::read(&mut BufReader::new(file), debug_log)?;
+        tex.name = filename.file_name().map(|name| name.to_string_lossy().into_owned());
+        Ok(tex)
+    }
+
+    /// Reads the header, the tables and the pixels; nothing is decoded yet.
+    pub fn read<R: Read + Seek>(reader: &mut R, debug_log: &mut Vec<String>) -> Result<Self> {
+        Self::read_with_progress(reader, debug_log, |_| ControlFlow::Continue(()))
+    }
+
+    /// Like [`TexFile::read`], calling `progress` once the file is read.
+    pub fn read_with_progress<R, F>(
+        reader: &mut R,
+        debug_log: &mut Vec<String>,
+        mut progress: F,
+    ) -> Result<Self>
+    where
+        R: Read + Seek,
+        F: FnMut(Progress) -> ControlFlow<()>,
+    {
+        let file_len = reader.seek(SeekFrom::End(0)).map_err(IlffError::io(0))?;
+        reader.seek(SeekFrom::Start(0)).map_err(IlffError::io(0))?;
+
+        check_header_size(file_len, 4)?;
+        let magic = reader.read_u32::<LittleEndian>().map_err(IlffError::io(0))?;
+        debug_log.push(format!("Read magic number: 0x{:08X}", magic));
+        if magic != MAGIC_LOOP {
+            debug_log.push("Invalid magic number!".to_string());
+            return Err(IlffError::BadMagic { offset: 0, expected: MAGIC_LOOP, actual: magic });
+        }
+        check_header_size(file_len, LoopHeader::SIZE)?;
+
+        let mut fields = [0u32; 4];
+        reader
+            .read_u32_into::<LittleEndian>(&mut fields)
+            .map_err(IlffError::io(4))?;
+        let [version, mode, mip_count, image_count] = fields;
+        let width = reader.read_u16::<LittleEndian>().map_err(IlffError::io(20))?;
+        let height = reader.read_u16::<LittleEndian>().map_err(IlffError::io(22))?;
+        let unk1 = reader.read_u32::<LittleEndian>().map_err(IlffError::io(24))?;
+        let unk2 = reader.read_u32::<LittleEndian>().map_err(IlffError::io(28))?;
+        let header = LoopHeader { version, mode, mip_count, image_count, width, height, unk1, unk2 };
+        debug_log.push(format!(
+            "LOOP version {} | Mode: {} | Resolution: {}x{} | Mip levels: {} | Sub-images: {}",
+            version, mode, width, height, mip_count, image_count
+        ));
+
+        let tables_end = LoopHeader::SIZE as u64 + header.tables_size();
+        if tables_end > file_len {
+            return Err(IlffError::TruncatedTexture {
+                offset: LoopHeader::SIZE as u64,
+                expected: header.tables_size(),
+                actual: file_len.saturating_sub(LoopHeader::SIZE as u64),
+            });
+        }
+        let mut mip_sizes = Vec::with_capacity(mip_count as usize);
+        for i in 0..mip_count as u64 {
+            let offset = LoopHeader::SIZE as u64 + i * 4;
+            let mip_width = reader.read_u16::<LittleEndian>().map_err(IlffError::io(offset))?;
+            let mip_height = reader.read_u16::<LittleEndian>().map_err(IlffError::io(offset + 2))?;
+            mip_sizes.push((mip_width, mip_height));
+        }
+        let mut sub_images = Vec::with_capacity(image_count as usize);
+        for i in 0..image_count as u64 {
+            let offset = LoopHeader::SIZE as u64 + mip_count as u64 * 4 + i * 8;
+            let mut fields = [0u16; 4];
+            reader
+                .read_u16_into::<LittleEndian>(&mut fields)
+                .map_err(IlffError::io(offset))?;
+            let [x, y, width, height] = fields;
+            sub_images.push(SubImage { x, y, width, height });
+        }
+
+        let mut pixels = Vec::with_capacity((file_len - tables_end) as usize);
+        reader.read_to_end(&mut pixels).map_err(IlffError::io(tables_end))?;
+
+        let update = Progress { bytes_read: file_len, total_bytes: file_len, chunks: 0 };
+        if progress(update).is_break() {
+            debug_log.push("Reading cancelled.".to_string());
+            return Err(IlffError::Cancelled { offset: file_len });
+        }
+
+        Ok(Self { name: None, header, mip_sizes, sub_images, pixels })
+    }
+
+    /// Position of the first pixel in the file.
+    pub fn data_offset(&self) -> u64 {
+        LoopHeader::SIZE as u64 + self.header.tables_size()
+    }
+
+    /// Decodes the texture with the same pixel formats as `BODY` chunks, and
+    /// a mip level per entry of the mip table.
+    ///
+    /// Fails when the mode is not a known pixel format or the file is too
+    /// short for the full-size image. Mip levels that don't fit are dropped.
+    pub fn images(&self, debug_log: &mut Vec<String>) -> Result<Vec<ImageResource>> {
+        let LoopHeader { mode, width, height, .. } = self.header;
+        let Some(format) = PixelFormat::from_body_type(mode) else {
+            return Err(IlffError::UnsupportedMode { offset: 8, mode });
+        };
+        let expected = format.image_size(width, height) as u64;
+        if (self.pixels.len() as u64) < expected {
+            return Err(IlffError::TruncatedTexture {
+                offset: self.data_offset(),
+                expected,
+                actual: self.pixels.len() as u64,
+            });
+        }
+
+        let (width_2, height_2) = self.mip_sizes.first().copied().unwrap_or_default();
+        let header = TextureHeader {
+            body_type: mode,
+            width_1: width,
+            height_1: height,
+            width_2,
+            height_2,
+            ..TextureHeader::default()
+        };
+        let data_offset = self.data_offset() as usize;
+        let name = self.name.clone();
+        let mut image =
+            texture::decode_texture(&self.pixels, header, &self.mip_sizes, data_offset, name, 0, debug_log)
+                .ok_or(IlffError::UnsupportedMode { offset: 8, mode })?;
+        for (i, sub) in self.sub_images.iter().enumerate() {
+            if !sub.fits(width, height) {
+                debug_log.push(format!(
+                    "Sub-image {} ({}x{} at {},{}) is outside the {}x{} texture.",
+                    i, sub.width, sub.height, sub.x, sub.y, width, height
+                ));
+            }
+        }
+        image.sub_images = self.sub_images.clone();
+        debug_log.push(format!(
+            "Loaded image: {:?} | Resolution: {}x{} | Format: {} | Mip levels: {} | Sub-images: {}",
+            image.name,
+            image.width,
+            image.height,
+            image.format,
+            image.level_count(),
+            image.sub_images.len()
+        ));
+        Ok(vec![image])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::container::ResourceFile;
+
+    /// A `LOOP` file with the given mip and sub-image tables, followed by
+    /// `pixels`.
+    fn loop_bytes(
+        mode: u32,
+        size: (u16, u16),
+        mips: &[(u16, u16)],
+        subs: &[[u16; 4]],
+        pixels: &[u8],
+    ) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(b"LOOP");
+        for field in [1, mode, mips.len() as u32, subs.len() as u32] {
+            bytes.extend_from_slice(&field.to_le_bytes());
+        }
+        bytes.extend_from_slice(&size.0.to_le_bytes());
+        bytes.extend_from_slice(&size.1.to_le_bytes());
+        bytes.extend_from_slice(&[0; 8]);
+        for &(width, height) in mips {
+            bytes.extend_from_slice(&width.to_le_bytes());
+            bytes.extend_from_slice(&height.to_le_bytes());
+        }
+        for field in subs.iter().flatten() {
+            bytes.extend_from_slice(&field.to_le_bytes());
+        }
+        bytes.extend_from_slice(pixels);
+        bytes
+    }
+
+    /// `count` RGB565 pixels of pure red.
+    fn red_565(count: usize) -> Vec<u8> {
+        0xF800u16.to_le_bytes().repeat(count)
+    }
+
+    fn read(bytes: &[u8]) -> Result<TexFile> {
+        TexFile::read(&mut Cursor::new(bytes), &mut Vec::new())
+    }
+
+    fn read_resource(bytes: &[u8]) -> Result<ResourceFile> {
+        ResourceFile::read_with_progress(&mut Cursor::new(bytes), &mut Vec::new(), |_| ControlFlow::Continue(()))
+    }
+
+    #[test]
+    fn plain_texture() {
+        let tex = read(&loop_bytes(1, (2, 3), &[], &[], &red_565(6))).unwrap();
+        assert_eq!(tex.header.version, 1);
+        assert_eq!((tex.header.width, tex.header.height), (2, 3));
+        assert_eq!(tex.data_offset(), 32);
+
+        let [image] = &tex.images(&mut Vec::new()).unwrap()[..] else {
+            panic!("expected one image");
+        };
+        assert_eq!((image.width, image.height, image.format), (2, 3, PixelFormat::Rgb565));
+        assert_eq!(image.level_count(), 1);
+        assert_eq!(image.data_offset, 32);
+        assert_eq!(image.data, [255, 0, 0, 255].repeat(6));
+        assert!(image.sub_images.is_empty());
+    }
+
+    #[test]
+    fn mip_sizes_come_from_the_table() {
+        // 3x2 and 1x1 rather than the 2x2 and 1x1 halving 4x4 would give.
+        let pixels = red_565(16 + 6 + 1);
+        let sub = [0, 0, 2, 2];
+        let tex = read(&loop_bytes(1, (4, 4), &[(3, 2), (1, 1)], &[sub], &pixels)).unwrap();
+        assert_eq!(tex.mip_sizes, [(3, 2), (1, 1)]);
+        assert_eq!(tex.sub_images, [SubImage { x: 0, y: 0, width: 2, height: 2 }]);
+        assert_eq!(tex.data_offset(), 32 + 8 + 8);
+
+        let image = tex.images(&mut Vec::new()).unwrap().remove(0);
+        let sizes: Vec<_> = image.mipmaps.iter().map(|mip| (mip.width, mip.height)).collect();
+        assert_eq!(sizes, [(3, 2), (1, 1)]);
+        assert_eq!(image.mipmaps[0].data, [255, 0, 0, 255].repeat(6));
+    }
+
+    #[test]
+    fn truncated_tables() {
+        let mut bytes = loop_bytes(1, (4, 4), &[(2, 2), (1, 1)], &[], &[]);
+        bytes.truncate(bytes.len() - 2);
+        let err = read(&bytes).unwrap_err();
+        assert!(
+            matches!(err, IlffError::TruncatedTexture { offset: 32, expected: 8, actual: 6 }),
+            "{err:?}"
+        );
+    }
+
+    #[test]
+    fn truncated_pixels() {
+        let tex = read(&loop_bytes(1, (4, 4), &[(2, 2)], &[], &red_565(15))).unwrap();
+        let err = tex.images(&mut Vec::new()).unwrap_err();
+        assert!(
+            matches!(err, IlffError::TruncatedTexture { offset: 36, expected: 32, actual: 30 }),
+            "{err:?}"
+        );
+    }
+
+    #[test]
+    fn unsupported_mode() {
+        let tex = read(&loop_bytes(99, (1, 1), &[], &[], &[0; 4])).unwrap();
+        let err = tex.images(&mut Vec::new()).unwrap_err();
+        assert!(matches!(err, IlffError::UnsupportedMode { offset: 8, mode: 99 }), "{err:?}");
+    }
+
+    #[test]
+    fn resource_file_tells_loop_from_ilff() {
+        let tex = loop_bytes(1, (1, 1), &[], &[], &red_565(1));
+        assert!(matches!(read_resource(&tex).unwrap(), ResourceFile::Tex(_)));
+
+        let mut ilff = b"ILFF".to_vec();
+        for field in [20u32, 4, 0] {
+            ilff.extend_from_slice(&field.to_le_bytes());
+        }
+        ilff.extend_from_slice(b"IRES");
+        assert!(matches!(read_resource(&ilff).unwrap(), ResourceFile::Ilff(_)));
+
+        let err = read_resource(b"RIFF\0\0\0\0").unwrap_err();
+        assert!(matches!(err, IlffError::UnknownMagic { actual: 0x46464952 }), "{err:?}");
+    }
+}
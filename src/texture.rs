@@ -0,0 +1,741 @@
+//! Textures stored in `BODY` chunks.
+
+use byteorder::{ByteOrder, LittleEndian};
+use image::imageops::{self, FilterType};
+use image::RgbaImage;
+
+use crate::chunk::{CHUNK_TYPE_BODY, CHUNK_TYPE_NAME};
+use crate::container::IlffFile;
+use crate::error::{IlffError, Result};
+use crate::format::{PixelFormat, BODY_TYPE_32BIT};
+use crate::resource::{self, ResourceKind};
+
+/// Size of the header at the start of every texture `BODY` chunk.
+pub const BODY_SUBHEADER_SIZE: u32 = 32;
+
+/// Name, offset and size of each field of the `BODY` sub-header, little
+/// endian. Only `body_type` and the first width/height pair are understood;
+/// the second pair, when set, is the size of the first mip level.
+pub const BODY_SUBHEADER_FIELDS: [(&str, usize, usize); 11] = [
+    ("body_type", 0, 4),
+    ("unk1", 4, 4),
+    ("unk2", 8, 4),
+    ("unk3", 12, 4),
+    ("unk4", 16, 4),
+    ("unk5", 20, 2),
+    ("width_1", 22, 2),
+    ("height_1", 24, 2),
+    ("width_2", 26, 2),
+    ("height_2", 28, 2),
+    ("unk6", 30, 2),
+];
+
+/// The sub-header at the start of a texture `BODY` chunk, with every field
+/// as stored, see [`BODY_SUBHEADER_FIELDS`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TextureHeader {
+    pub body_type: u32,
+    pub unk1: u32,
+    pub unk2: u32,
+    pub unk3: u32,
+    pub unk4: u32,
+    pub unk5: u16,
+    pub width_1: u16,
+    pub height_1: u16,
+    /// Either 0, a copy of `width_1` or the width of the first mip level.
+    pub width_2: u16,
+    pub height_2: u16,
+    pub unk6: u16,
+}
+
+impl TextureHeader {
+    /// Reads the sub-header from the start of a `BODY` payload.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `payload` is shorter than [`BODY_SUBHEADER_SIZE`].
+    pub fn read(payload: &[u8]) -> Self {
+        let u32_at = |pos: usize| LittleEndian::read_u32(&payload[pos..]);
+        let u16_at = |pos: usize| LittleEndian::read_u16(&payload[pos..]);
+        Self {
+            body_type: u32_at(0),
+            unk1: u32_at(4),
+            unk2: u32_at(8),
+            unk3: u32_at(12),
+            unk4: u32_at(16),
+            unk5: u16_at(20),
+            width_1: u16_at(22),
+            height_1: u16_at(24),
+            width_2: u16_at(26),
+            height_2: u16_at(28),
+            unk6: u16_at(30),
+        }
+    }
+
+    /// Writes the sub-header over the start of `payload`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `payload` is shorter than [`BODY_SUBHEADER_SIZE`].
+    pub fn write(&self, payload: &mut [u8]) {
+        for ((_, offset, size), value) in BODY_SUBHEADER_FIELDS.iter().zip(self.values()) {
+            match size {
+                4 => LittleEndian::write_u32(&mut payload[*offset..], value),
+                _ => LittleEndian::write_u16(&mut payload[*offset..], value as u16),
+            }
+        }
+    }
+
+    /// Every field widened to `u32`, in the order of [`BODY_SUBHEADER_FIELDS`].
+    pub fn values(&self) -> [u32; 11] {
+        [
+            self.body_type,
+            self.unk1,
+            self.unk2,
+            self.unk3,
+            self.unk4,
+            self.unk5 as u32,
+            self.width_1 as u32,
+            self.height_1 as u32,
+            self.width_2 as u32,
+            self.height_2 as u32,
+            self.unk6 as u32,
+        ]
+    }
+}
+
+/// A decoded texture together with the name from its `NAME` chunk.
+#[derive(Debug, Clone)]
+pub struct ImageResource {
+    pub name: Option<String>,
+    /// Index of the `BODY` chunk in [`IlffFile::chunks`].
+    pub chunk_index: usize,
+    pub width: u16,
+    pub height: u16,
+    /// Pixel format of `raw`, taken from the `body_type` field.
+    pub format: PixelFormat,
+    /// Whether `format` was guessed from the pixels because the header
+    /// doesn't record the channel order.
+    pub format_guessed: bool,
+    /// Pixels as stored in the file.
+    pub raw: Vec<u8>,
+    /// Pixels converted to unmultiplied RGBA8, `width * height * 4` bytes.
+    pub data: Vec<u8>,
+    /// The smaller mip levels that follow the full-size image, largest first.
+    pub mipmaps: Vec<MipLevel>,
+    /// The `BODY` sub-header as read, including the fields not understood
+    /// yet. Loose `.tex` files fill in the fields their `LOOP` header has.
+    pub header: TextureHeader,
+    /// Position of the first pixel from the start of the `BODY` payload, or
+    /// from the start of the file for loose `.tex` files.
+    pub data_offset: usize,
+    /// Frames of an atlas such as a font or HUD sprite sheet, in the order of
+    /// the file's sub-image table. Empty for plain textures.
+    pub sub_images: Vec<SubImage>,
+}
+
+/// A rectangle of the full-size image of an atlas.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SubImage {
+    pub x: u16,
+    pub y: u16,
+    pub width: u16,
+    pub height: u16,
+}
+
+impl SubImage {
+    /// Whether the rectangle is non-empty and lies within a `width` x
+    /// `height` image.
+    pub fn fits(&self, width: u16, height: u16) -> bool {
+        self.width > 0
+            && self.height > 0
+            && self.x as u32 + self.width as u32 <= width as u32
+            && self.y as u32 + self.height as u32 <= height as u32
+    }
+}
+
+/// One reduced-size copy of a texture, in the same pixel format.
+#[derive(Debug, Clone)]
+pub struct MipLevel {
+    pub width: u16,
+    pub height: u16,
+    pub raw: Vec<u8>,
+    pub data: Vec<u8>,
+}
+
+impl ImageResource {
+    /// Reinterprets `raw` as `format` and converts it to RGBA8 again, for
+    /// textures whose channel order was detected wrongly.
+    ///
+    /// `format` must have the same number of bytes per pixel as the current
+    /// one; other formats are ignored.
+    pub fn set_format(&mut self, format: PixelFormat) {
+        if format.bytes_per_pixel() != self.format.bytes_per_pixel() {
+            return;
+        }
+        self.format = format;
+        self.data = format.to_rgba8(&self.raw);
+        for mip in &mut self.mipmaps {
+            mip.data = format.to_rgba8(&mip.raw);
+        }
+    }
+
+    /// Number of mip levels, including the full-size image.
+    pub fn level_count(&self) -> usize {
+        1 + self.mipmaps.len()
+    }
+
+    /// Raw bytes of pixel (`x`, `y`) of mip `level`, in [`ImageResource::format`].
+    pub fn raw_pixel(&self, level: usize, x: u16, y: u16) -> &[u8] {
+        let (width, raw) = match level {
+            0 => (self.width, &self.raw),
+            _ => (self.mipmaps[level - 1].width, &self.mipmaps[level - 1].raw),
+        };
+        let bpp = self.format.bytes_per_pixel();
+        let start = (y as usize * width as usize + x as usize) * bpp;
+        &raw[start..start + bpp]
+    }
+
+    /// Position of pixel (`x`, `y`) of mip `level` from the start of the
+    /// `BODY` payload; add [`Chunk::payload_offset`] for the position in the
+    /// file. For loose `.tex` files it is the position in the file.
+    ///
+    /// [`Chunk::payload_offset`]: crate::chunk::Chunk::payload_offset
+    pub fn pixel_offset(&self, level: usize, x: u16, y: u16) -> usize {
+        let before: usize = std::iter::once(self.raw.len())
+            .chain(self.mipmaps.iter().map(|mip| mip.raw.len()))
+            .take(level)
+            .sum();
+        let width = self.level(level).0;
+        let bpp = self.format.bytes_per_pixel();
+        self.data_offset + before + (y as usize * width as usize + x as usize) * bpp
+    }
+
+    /// The RGBA8 pixels of sub-image `index`, cut from the full-size image.
+    ///
+    /// Returns `None` when there is no such sub-image or its rectangle
+    /// doesn't fit in the image.
+    pub fn sub_image(&self, index: usize) -> Option<RgbaImage> {
+        let sub = self.sub_images.get(index)?;
+        if !sub.fits(self.width, self.height) {
+            return None;
+        }
+        let full = RgbaImage::from_raw(self.width as u32, self.height as u32, self.data.clone())?;
+        let (x, y, w, h) = (sub.x as u32, sub.y as u32, sub.width as u32, sub.height as u32);
+        Some(imageops::crop_imm(&full, x, y, w, h).to_image())
+    }
+
+    /// Width, height and RGBA8 pixels of mip `level`, where level 0 is the
+    /// full-size image.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `level` is not below [`ImageResource::level_count`].
+    pub fn level(&self, level: usize) -> (u16, u16, &[u8]) {
+        match level {
+            0 => (self.width, self.height, &self.data),
+            _ => {
+                let mip = &self.mipmaps[level - 1];
+                (mip.width, mip.height, &mip.data)
+            }
+        }
+    }
+}
+
+/// Interprets the `NAME`/`BODY` pairs of `file` as textures.
+///
+/// Every `BODY` takes the name of the last `NAME` before it; other chunks, and
+/// bodies holding other kinds of resources (see [`resource`]), are skipped.
+/// Progress is appended to `debug_log`.
+pub fn images(file: &IlffFile, debug_log: &mut Vec<String>) -> Result<Vec<ImageResource>> {
+    let mut images = Vec::new();
+    let mut current_name: Option<String> = None;
+
+    for (chunk_index, chunk) in file.chunks.iter().enumerate() {
+        match chunk.tag {
+            CHUNK_TYPE_NAME => {
+                let name = resource::chunk_name(&chunk.payload);
+                debug_log.push(format!("Found NAME chunk: {}", name));
+                current_name = Some(name);
+            }
+            CHUNK_TYPE_BODY => {
+                debug_log.push("Found BODY chunk.".to_string());
+                let kind = ResourceKind::detect(current_name.as_deref(), &chunk.payload);
+                if kind != ResourceKind::Texture {
+                    debug_log.push(format!("Skipping {} resource {:?}.", kind, current_name));
+                    continue;
+                }
+                if chunk.buffer_size < BODY_SUBHEADER_SIZE {
+                    debug_log.push("Invalid buffer size for BODY chunk.".to_string());
+                    return Err(IlffError::ChunkTooSmall {
+                        offset: chunk.offset,
+                        chunk_index,
+                        tag: chunk.tag,
+                        expected: BODY_SUBHEADER_SIZE,
+                        actual: chunk.buffer_size,
+                    });
+                }
+                if let Some(image) =
+                    read_body(&chunk.payload, current_name.clone(), chunk_index, debug_log)
+                {
+                    debug_log.push(format!(
+                        "Loaded image: {:?} | Resolution: {}x{} | Format: {} | Mip levels: {} | Size: {} bytes",
+                        image.name,
+                        image.width,
+                        image.height,
+                        image.format,
+                        image.level_count(),
+                        image.raw.len()
+                    ));
+                    images.push(image);
+                }
+            }
+            _ => {
+                debug_log.push(format!("Skipping unknown chunk type: 0x{:08X}", chunk.tag));
+            }
+        }
+    }
+
+    Ok(images)
+}
+
+/// Reads a texture from the payload of a `BODY` chunk.
+///
+/// Returns `None` when the pixel format is not supported or the payload is
+/// too small for the resolution in its header; the reason is written to
+/// `debug_log`.
+///
+/// # Panics
+///
+/// Panics if `payload` is shorter than [`BODY_SUBHEADER_SIZE`].
+pub fn read_body(
+    payload: &[u8],
+    name: Option<String>,
+    chunk_index: usize,
+    debug_log: &mut Vec<String>,
+) -> Option<ImageResource> {
+    let header = TextureHeader::read(payload);
+    let data_offset = BODY_SUBHEADER_SIZE as usize;
+    let mip_sizes = mip_chain((header.width_1, header.height_1), (header.width_2, header.height_2));
+    decode_texture(&payload[data_offset..], header, &mip_sizes, data_offset, name, chunk_index, debug_log)
+}
+
+/// Decodes the full-size image and mip levels in `pixels` as described by
+/// `header`, with the mip levels sized by `mip_sizes`. Shared by `BODY`
+/// chunks and loose `.tex` files; `data_offset` is where `pixels` starts,
+/// see [`ImageResource::data_offset`].
+///
+/// Returns `None` when the pixel format is not supported or `pixels` is too
+/// small for the resolution in `header`; the reason is written to
+/// `debug_log`.
+pub(crate) fn decode_texture(
+    pixels: &[u8],
+    header: TextureHeader,
+    mip_sizes: &[(u16, u16)],
+    data_offset: usize,
+    name: Option<String>,
+    chunk_index: usize,
+    debug_log: &mut Vec<String>,
+) -> Option<ImageResource> {
+    let TextureHeader { body_type, width_1, height_1, .. } = header;
+
+    let mut image_data = pixels.to_vec();
+
+    let Some(format) = PixelFormat::from_body_type(body_type) else {
+        debug_log.push(format!(
+            "Unsupported BODY type {} for {:?}, skipping texture.",
+            body_type, name
+        ));
+        return None;
+    };
+
+    let expected_size = format.image_size(width_1, height_1);
+    if image_data.len() < expected_size {
+        debug_log.push("Truncating image data due to unexpected size.".to_string());
+        return None;
+    }
+    let mip_data = image_data.split_off(expected_size);
+
+    let format_guessed = body_type == BODY_TYPE_32BIT;
+    let format = if format_guessed {
+        let guess = PixelFormat::guess_32bit_order(&image_data);
+        debug_log.push(format!("Channel order not in header, guessed {}.", guess));
+        guess
+    } else {
+        format
+    };
+
+    let mipmaps = read_mipmaps(&mip_data, format, mip_sizes, debug_log);
+
+    Some(ImageResource {
+        name,
+        chunk_index,
+        width: width_1,
+        height: height_1,
+        format,
+        format_guessed,
+        data: format.to_rgba8(&image_data),
+        raw: image_data,
+        mipmaps,
+        header,
+        data_offset,
+        sub_images: Vec::new(),
+    })
+}
+
+/// Replaces the pixels of `image` in `file` with `pixels`, which may have a
+/// different size.
+///
+/// The pixels are encoded in the format the header's `body_type` names, or
+/// in `image.format` when the header doesn't say (see
+/// [`ImageResource::format_guessed`]), so a "treat as" override never ends
+/// up in the file. As many mip levels as `image` had are generated by
+/// downscaling. The rest of the `BODY` sub-header, the `NAME` chunk and all
+/// other chunks are kept as they are. Returns the texture read back from
+/// the new payload.
+///
+/// # Panics
+///
+/// Panics if `pixels` is larger than 65535 in either direction.
+pub fn replace_texture(
+    file: &mut IlffFile,
+    image: &ImageResource,
+    pixels: &RgbaImage,
+    debug_log: &mut Vec<String>,
+) -> Option<ImageResource> {
+    let width = u16::try_from(pixels.width()).expect("texture width above 65535");
+    let height = u16::try_from(pixels.height()).expect("texture height above 65535");
+    let chunk = &mut file.chunks[image.chunk_index];
+    let format = match image.format_guessed {
+        true => image.format,
+        false => PixelFormat::from_body_type(image.header.body_type).unwrap_or(image.format),
+    };
+
+    let mut payload = chunk.payload[..BODY_SUBHEADER_SIZE as usize].to_vec();
+    let first_mip = encode_levels(pixels, format, image.mipmaps.len(), &mut payload);
+
+    // The second pair is either unset, a copy of the first or the first mip size.
+    let mut header = TextureHeader::read(&payload);
+    let old_second = (header.width_2, header.height_2);
+    let second = match first_mip {
+        _ if old_second == (0, 0) => old_second,
+        Some(size) if old_second != (image.width, image.height) => size,
+        _ => (width, height),
+    };
+
+    (header.width_1, header.height_1) = (width, height);
+    (header.width_2, header.height_2) = second;
+    header.write(&mut payload);
+
+    debug_log.push(format!(
+        "Replacing texture {:?} with {}x{} {} pixels.",
+        image.name, width, height, format
+    ));
+    chunk.payload = payload;
+    file.update_layout();
+
+    let chunk = &file.chunks[image.chunk_index];
+    let mut replaced = read_body(&chunk.payload, image.name.clone(), image.chunk_index, debug_log)?;
+    replaced.set_format(format);
+    Some(replaced)
+}
+
+/// Builds a `BODY` payload for a new texture, with `mip_count` mip levels
+/// generated by downscaling.
+///
+/// Fails if `format` has no `body_type`. Fields other than the type and the
+/// sizes are left at 0.
+///
+/// # Panics
+///
+/// Panics if `pixels` is larger than 65535 in either direction.
+pub fn new_body(pixels: &RgbaImage, format: PixelFormat, mip_count: usize) -> Option<Vec<u8>> {
+    let body_type = format.body_type()?;
+    let width = u16::try_from(pixels.width()).expect("texture width above 65535");
+    let height = u16::try_from(pixels.height()).expect("texture height above 65535");
+
+    let mut payload = vec![0u8; BODY_SUBHEADER_SIZE as usize];
+    let first_mip = encode_levels(pixels, format, mip_count, &mut payload);
+    let (width_2, height_2) = first_mip.unwrap_or((width, height));
+    let header = TextureHeader {
+        body_type,
+        width_1: width,
+        height_1: height,
+        width_2,
+        height_2,
+        ..TextureHeader::default()
+    };
+    header.write(&mut payload);
+    Some(payload)
+}
+
+/// Appends `pixels` and up to `mip_count` halved copies of it to `out` in
+/// `format`. Returns the size of the first mip level, if any was written.
+fn encode_levels(
+    pixels: &RgbaImage,
+    format: PixelFormat,
+    mip_count: usize,
+    out: &mut Vec<u8>,
+) -> Option<(u16, u16)> {
+    out.extend(format.from_rgba8(pixels.as_raw()));
+
+    let (mut w, mut h) = (pixels.width() as u16, pixels.height() as u16);
+    let mut first_mip = None;
+    for _ in 0..mip_count {
+        if (w, h) == (1, 1) {
+            break;
+        }
+        (w, h) = ((w / 2).max(1), (h / 2).max(1));
+        first_mip.get_or_insert((w, h));
+        let mip = imageops::resize(pixels, w as u32, h as u32, FilterType::Triangle);
+        out.extend(format.from_rgba8(mip.as_raw()));
+    }
+    first_mip
+}
+
+/// Number of mip levels below a `width` x `height` image, down to 1x1.
+pub fn full_mip_count(width: u16, height: u16) -> usize {
+    (16 - width.max(height).max(1).leading_zeros() - 1) as usize
+}
+
+/// Sizes of the mip levels below a `width` x `height` `BODY` texture.
+///
+/// The second width/height pair of the sub-header is the size of the first
+/// mip level when it is set; each further level halves the previous one,
+/// down to 1x1.
+fn mip_chain((width, height): (u16, u16), second: (u16, u16)) -> Vec<(u16, u16)> {
+    let half = |(w, h): (u16, u16)| ((w / 2).max(1), (h / 2).max(1));
+    let (w2, h2) = second;
+    let second_is_mip = w2 > 0 && h2 > 0 && w2 <= width && h2 <= height && second != (width, height);
+    let mut sizes = Vec::new();
+    if (width, height) == (1, 1) {
+        return sizes;
+    }
+    let mut size = if second_is_mip { second } else { half((width, height)) };
+    loop {
+        sizes.push(size);
+        if size == (1, 1) {
+            return sizes;
+        }
+        size = half(size);
+    }
+}
+
+/// Splits the bytes after the full-size image into mip levels of `sizes`.
+/// Reading stops at an empty size or when the remaining bytes don't fill
+/// the next level.
+fn read_mipmaps(
+    mut bytes: &[u8],
+    format: PixelFormat,
+    sizes: &[(u16, u16)],
+    debug_log: &mut Vec<String>,
+) -> Vec<MipLevel> {
+    let mut mipmaps = Vec::new();
+    for &(width, height) in sizes {
+        let size = format.image_size(width, height);
+        if width == 0 || height == 0 || bytes.len() < size {
+            break;
+        }
+        let (raw, rest) = bytes.split_at(size);
+        mipmaps.push(MipLevel {
+            width,
+            height,
+            raw: raw.to_vec(),
+            data: format.to_rgba8(raw),
+        });
+        bytes = rest;
+    }
+
+    if !bytes.is_empty() {
+        debug_log.push(format!("Ignoring {} trailing bytes after the mip levels.", bytes.len()));
+    }
+    mipmaps
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Rgba;
+
+    use super::*;
+    use crate::pack::{self, PackEntry};
+
+    /// A `BODY` payload of `format` with the given width/height pairs,
+    /// followed by `pixels` pixels of 0xFF bytes.
+    fn body(format: PixelFormat, first: (u16, u16), second: (u16, u16), pixels: usize) -> Vec<u8> {
+        let header = TextureHeader {
+            body_type: format.body_type().unwrap(),
+            width_1: first.0,
+            height_1: first.1,
+            width_2: second.0,
+            height_2: second.1,
+            ..TextureHeader::default()
+        };
+        let mut payload = vec![0u8; BODY_SUBHEADER_SIZE as usize];
+        header.write(&mut payload);
+        payload.resize(payload.len() + pixels * format.bytes_per_pixel(), 0xFF);
+        payload
+    }
+
+    fn mip_sizes(image: &ImageResource) -> Vec<(u16, u16)> {
+        image.mipmaps.iter().map(|mip| (mip.width, mip.height)).collect()
+    }
+
+    #[test]
+    fn unset_second_pair_halves() {
+        assert_eq!(mip_chain((4, 2), (0, 0)), [(2, 1), (1, 1)]);
+        let payload = body(PixelFormat::Rgb565, (4, 2), (0, 0), 8 + 2 + 1);
+        let image = read_body(&payload, None, 0, &mut Vec::new()).unwrap();
+        assert_eq!(mip_sizes(&image), [(2, 1), (1, 1)]);
+    }
+
+    #[test]
+    fn copied_second_pair_halves() {
+        assert_eq!(mip_chain((4, 4), (4, 4)), [(2, 2), (1, 1)]);
+        let payload = body(PixelFormat::Rgb565, (4, 4), (4, 4), 16 + 4 + 1);
+        let image = read_body(&payload, None, 0, &mut Vec::new()).unwrap();
+        assert_eq!(mip_sizes(&image), [(2, 2), (1, 1)]);
+    }
+
+    #[test]
+    fn second_pair_sizes_the_first_mip() {
+        assert_eq!(mip_chain((8, 8), (6, 6)), [(6, 6), (3, 3), (1, 1)]);
+        let payload = body(PixelFormat::Argb1555, (8, 8), (6, 6), 64 + 36 + 9 + 1);
+        let image = read_body(&payload, None, 0, &mut Vec::new()).unwrap();
+        assert_eq!(mip_sizes(&image), [(6, 6), (3, 3), (1, 1)]);
+    }
+
+    #[test]
+    fn second_pair_larger_than_the_image_is_ignored() {
+        assert_eq!(mip_chain((4, 4), (8, 2)), [(2, 2), (1, 1)]);
+    }
+
+    #[test]
+    fn non_square_chains_end_at_1x1() {
+        assert_eq!(mip_chain((8, 2), (0, 0)), [(4, 1), (2, 1), (1, 1)]);
+        assert_eq!(mip_chain((1, 4), (1, 2)), [(1, 2), (1, 1)]);
+        assert!(mip_chain((1, 1), (0, 0)).is_empty());
+    }
+
+    #[test]
+    fn trailing_bytes_too_short_for_a_level_are_ignored() {
+        // A full 2x2 level, then half of the 1x1 one.
+        let mut payload = body(PixelFormat::Rgb565, (4, 4), (0, 0), 16 + 4);
+        payload.push(0xFF);
+        let mut log = Vec::new();
+        let image = read_body(&payload, None, 0, &mut log).unwrap();
+        assert_eq!(mip_sizes(&image), [(2, 2)]);
+        assert!(log.iter().any(|line| line == "Ignoring 1 trailing bytes after the mip levels."), "{log:?}");
+    }
+
+    /// An archive of a 4x4 texture of `format` between two others, and the
+    /// textures read from it.
+    fn archive(format: PixelFormat, mipmaps: bool) -> (IlffFile, Vec<ImageResource>) {
+        let entry = |name: &str, format, mipmaps| PackEntry {
+            name: name.to_string(),
+            pixels: RgbaImage::from_pixel(4, 4, Rgba([0, 255, 0, 255])),
+            format,
+            mipmaps,
+        };
+        let file = pack::pack(&[
+            entry("a.tex", PixelFormat::Rgb565, true),
+            entry("b.tex", format, mipmaps),
+            entry("c.tex", PixelFormat::Argb4444, false),
+        ])
+        .unwrap();
+        let images = images(&file, &mut Vec::new()).unwrap();
+        (file, images)
+    }
+
+    fn red(width: u32, height: u32) -> RgbaImage {
+        RgbaImage::from_pixel(width, height, Rgba([255, 0, 0, 255]))
+    }
+
+    /// Replaces `image` and checks that every other chunk is unchanged and
+    /// that reading the file again gives the returned texture.
+    fn replace(file: &mut IlffFile, image: &ImageResource, pixels: &RgbaImage) -> ImageResource {
+        let before = file.clone();
+        let replaced = replace_texture(file, image, pixels, &mut Vec::new()).unwrap();
+        for (i, (old, new)) in before.chunks.iter().zip(&file.chunks).enumerate() {
+            if i != image.chunk_index {
+                assert_eq!((&old.payload, &old.trailing), (&new.payload, &new.trailing), "chunk {i}");
+            }
+        }
+        let reread = images(file, &mut Vec::new()).unwrap();
+        assert_eq!(reread[1].data, replaced.data);
+        assert_eq!(reread[1].header, replaced.header);
+        replaced
+    }
+
+    #[test]
+    fn replace_at_the_same_size() {
+        let (mut file, images) = archive(PixelFormat::Rgb565, false);
+        let replaced = replace(&mut file, &images[1], &red(4, 4));
+        assert_eq!((replaced.width, replaced.height), (4, 4));
+        assert_eq!(replaced.format, PixelFormat::Rgb565);
+        assert_eq!(replaced.level_count(), 1);
+        assert_eq!(replaced.data, red(4, 4).into_raw());
+        assert_eq!((replaced.header.width_2, replaced.header.height_2), (4, 4));
+    }
+
+    #[test]
+    fn replace_at_a_new_size_copies_the_first_pair() {
+        let (mut file, images) = archive(PixelFormat::Argb1555, false);
+        let replaced = replace(&mut file, &images[1], &red(8, 2));
+        assert_eq!((replaced.width, replaced.height), (8, 2));
+        assert_eq!(replaced.level_count(), 1);
+        let header = replaced.header;
+        assert_eq!((header.width_1, header.height_1, header.width_2, header.height_2), (8, 2, 8, 2));
+    }
+
+    #[test]
+    fn replace_with_mips_sets_the_first_mip_size() {
+        let (mut file, images) = archive(PixelFormat::Argb4444, true);
+        assert_eq!(images[1].level_count(), 3);
+        let replaced = replace(&mut file, &images[1], &red(8, 2));
+        assert_eq!(mip_sizes(&replaced), [(4, 1), (2, 1)]);
+        assert_eq!((replaced.header.width_2, replaced.header.height_2), (4, 1));
+        assert_eq!(replaced.mipmaps[0].data, red(4, 1).into_raw());
+    }
+
+    #[test]
+    fn replace_keeps_an_unset_second_pair() {
+        let (mut file, _) = archive(PixelFormat::Rgb565, true);
+        let chunk = &mut file.chunks[3];
+        let mut header = TextureHeader::read(&chunk.payload);
+        (header.width_2, header.height_2) = (0, 0);
+        header.write(&mut chunk.payload);
+        let images = images(&file, &mut Vec::new()).unwrap();
+
+        let replaced = replace(&mut file, &images[1], &red(2, 2));
+        assert_eq!((replaced.header.width_2, replaced.header.height_2), (0, 0));
+        assert_eq!(mip_sizes(&replaced), [(1, 1)]);
+    }
+
+    #[test]
+    fn replace_ignores_the_treat_as_override() {
+        let (mut file, mut images) = archive(PixelFormat::Bgra8888, false);
+        images[1].set_format(PixelFormat::Rgba8888);
+        let replaced = replace(&mut file, &images[1], &red(4, 4));
+        assert_eq!(replaced.format, PixelFormat::Bgra8888);
+        assert_eq!(replaced.header.body_type, PixelFormat::Bgra8888.body_type().unwrap());
+        assert_eq!(replaced.raw, PixelFormat::Bgra8888.from_rgba8(red(4, 4).as_raw()));
+    }
+
+    #[test]
+    fn replace_keeps_the_chosen_order_of_guessed_formats() {
+        let (mut file, _) = archive(PixelFormat::Bgra8888, false);
+        let chunk = &mut file.chunks[3];
+        let mut header = TextureHeader::read(&chunk.payload);
+        header.body_type = BODY_TYPE_32BIT;
+        header.write(&mut chunk.payload);
+        let mut images = images(&file, &mut Vec::new()).unwrap();
+        assert!(images[1].format_guessed);
+        images[1].set_format(PixelFormat::Argb8888);
+
+        let replaced = replace_texture(&mut file, &images[1], &red(4, 4), &mut Vec::new()).unwrap();
+        assert_eq!(replaced.format, PixelFormat::Argb8888);
+        assert_eq!(replaced.header.body_type, BODY_TYPE_32BIT);
+        assert_eq!(replaced.raw, PixelFormat::Argb8888.from_rgba8(red(4, 4).as_raw()));
+        assert_eq!(replaced.data, red(4, 4).into_raw());
+    }
+}